@@ -0,0 +1,87 @@
+//! Time of a move for growing snake lengths, against the previous body: a linked list where every
+//! node took the position and direction of the one before it on each move.
+//!
+//! Run with `cargo bench`.
+
+use std::collections::LinkedList;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use snake::snake::{Snake, SnakeNode};
+use snake::{Border, Direction, Playfield, Position};
+
+const LENGTHS: [usize; 4] = [10, 100, 1_000, 10_000];
+const MOVES: u32 = 20_000;
+
+/// The body as it was stored before: every move rewrites every node
+struct ListSnake {
+    direction: Direction,
+    list: LinkedList<(Position, Option<Direction>)>,
+}
+
+impl ListSnake {
+    fn update_positions(&mut self) {
+        let mut iterator = self.list.iter_mut();
+        let Some(head) = iterator.next() else {
+            return;
+        };
+        let mut previous = *head;
+        head.0.move_towards(self.direction);
+        head.1 = Some(self.direction);
+        for node in iterator {
+            std::mem::swap(node, &mut previous);
+        }
+    }
+}
+
+/// Cells of the playfield in a zigzag, line by line: each one is next to the one before
+fn zigzag(playfield: &Playfield, length: usize) -> Vec<Position> {
+    let width = usize::from(playfield.get_width());
+    let mut cells: Vec<Position> = playfield.positions().take(length).collect();
+    for line in cells.chunks_mut(width).skip(1).step_by(2) {
+        line.reverse();
+    }
+    cells
+}
+
+/// A snake of `length` cells folded over the playfield, the head on the last cell
+fn build(playfield: Playfield, length: usize) -> (Snake, ListSnake) {
+    let cells = zigzag(&playfield, length);
+    let head = cells[length - 1];
+    let direction = if length > 1 { cells[length - 2].direction_to(&head).unwrap_or(Direction::Right) } else { Direction::Right };
+
+    let mut snake = Snake::new(direction, SnakeNode::new(head), Some(playfield));
+    for position in cells.iter().rev().skip(1) {
+        snake.add_tail_at(*position);
+    }
+    let list = cells.iter().rev().map(|position| (*position, Some(direction))).collect();
+    (snake, ListSnake { direction, list })
+}
+
+fn time(mut step: impl FnMut()) -> Duration {
+    let started = Instant::now();
+    for _ in 0..MOVES {
+        step();
+    }
+    started.elapsed() / MOVES
+}
+
+fn main() {
+    // 200x100 cells, room for the longest snake
+    let playfield = Playfield::new(&Border::new(0, 201, 0, 101));
+    println!("{:>8}  {:>12}  {:>12}  {:>8}", "length", "VecDeque", "LinkedList", "speedup");
+    for length in LENGTHS {
+        let (mut snake, mut list_snake) = build(playfield, length);
+        let deque = time(|| {
+            black_box(snake.update_positions(None));
+        });
+        let linked = time(|| {
+            list_snake.update_positions();
+            black_box(&list_snake.list);
+        });
+        println!(
+            "{length:>8}  {:>12?}  {:>12?}  {:>7.1}x",
+            deque, linked, linked.as_secs_f64() / deque.as_secs_f64().max(f64::EPSILON)
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::info;
+
+use crate::backend::{AnsiBackend, SharedBuffer};
+use crate::drawing::Drawer;
+use crate::replay::Replay;
+use crate::screen::Screen;
+use crate::ESC;
+
+/// How long the last frame stays on screen
+const END_DELAY: Duration = Duration::from_secs(2);
+
+/// Escapes the text for a JSON string
+fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Writes an output event with the bytes the screen sent since the previous one
+fn write_event<W: Write>(out: &mut W, time: Duration, buffer: &SharedBuffer) -> io::Result<()> {
+    let bytes = buffer.take();
+    if bytes.is_empty() {
+        return Ok(());
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    writeln!(out, "[{:.6}, \"o\", {}]", time.as_secs_f64(), json_string(&text))
+}
+
+/// Plays the replay without a terminal and writes it as an asciinema v2 recording (`.cast`),
+/// every tick at the time it took in the game
+pub fn export<W: Write>(replay: &Replay, out: &mut W) -> io::Result<()> {
+    // Room for the score above the arena and the seed under it
+    let width = replay.border.end_col + 2;
+    let height = replay.border.end_line + 2;
+    let buffer = SharedBuffer::new();
+    let mut screen = Screen::with_backend(Box::new(AnsiBackend::with_writer(buffer.clone(), width, height)));
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let title = format!("snake, seed {}, score {}", replay.seed, replay.score);
+    writeln!(
+        out,
+        "{{\"version\": 2, \"width\": {width}, \"height\": {height}, \"timestamp\": {timestamp}, \"title\": {}, \"env\": {{\"TERM\": \"xterm-256color\"}}}}",
+        json_string(&title)
+    )?;
+
+    let settings = &replay.settings;
+    let palette = settings.palette();
+    let glyphs = settings.glyphs;
+    let mut state = replay.start();
+    let mut time = Duration::ZERO;
+    screen.init();
+    Drawer::draw_game(&mut screen, &state, &palette, &glyphs);
+    write_event(out, time, &buffer)?;
+
+    while state.get_tick() < replay.ticks {
+        let (duration, outcome) = replay.play_tick(&mut screen, &mut state);
+        time += duration;
+        write_event(out, time, &buffer)?;
+        if outcome.is_over() {
+            break;
+        }
+    }
+
+    // Puts the cursor back under the arena, so the player doesn't end on top of it
+    time += END_DELAY;
+    screen.show_cursor();
+    write!(buffer.clone(), "{ESC}[{height};1H")?;
+    write_event(out, time, &buffer)?;
+    info!("[Export] {} ticks exported", state.get_tick());
+    Ok(())
+}
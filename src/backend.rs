@@ -0,0 +1,255 @@
+use std::cell::RefCell;
+use std::io::{self, Stdout, Write};
+use std::rc::Rc;
+
+use crossterm::terminal::enable_raw_mode;
+use log::error;
+
+use crate::frame::{Cell, FrameBuffer};
+use crate::{Column, Height, Line, Width, ESC};
+
+/// Colors and text attributes of a drawn cell
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub color: Option<u16>, // 256 colors (8 bit), terminal default if None
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn colored(color: u16) -> Self {
+        Style { color: Some(color), underline: false }
+    }
+
+    pub fn underlined() -> Self {
+        Style { color: None, underline: true }
+    }
+}
+
+/// Output device the [`Screen`](crate::screen::Screen) draws on.
+/// Lines and columns are 1-based, like the ANSI cursor positions.
+pub trait RenderBackend {
+    /// Prepares the device before the first draw
+    fn init(&mut self) {}
+
+    /// Writes `text` from the given position to the right, every character using `style`
+    fn write(&mut self, line: Line, column: Column, text: &str, style: Style);
+
+    fn clear(&mut self);
+
+    fn set_cursor_visible(&mut self, visible: bool);
+
+    /// Asks the device to report focus gained and lost events, if it can
+    fn set_focus_reporting(&mut self, _enabled: bool) {}
+
+    fn flush(&mut self);
+
+    /// Returns width and height as tuple
+    fn size(&self) -> (Width, Height);
+
+    /// Reads the size again where it can change (a terminal being resized), then returns it
+    fn refresh_size(&mut self) -> (Width, Height) {
+        self.size()
+    }
+}
+
+/// Draws on a real terminal with ANSI escape codes
+pub struct AnsiBackend<W: Write = Stdout> {
+    out: W,
+    width: Width,
+    height: Height,
+    raw_mode: bool,
+}
+
+impl AnsiBackend {
+    pub fn new() -> Self {
+        let (width, height) = match terminal_size::terminal_size() {
+            Some((width, height)) => (width.0, height.0),
+            None => (0, 0),
+        };
+        AnsiBackend { out: io::stdout(), width, height, raw_mode: true }
+    }
+}
+
+impl Default for AnsiBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> AnsiBackend<W> {
+    /// Writes the escape codes to `out` instead of the terminal, pretending it has the given size
+    pub fn with_writer(out: W, width: Width, height: Height) -> Self {
+        AnsiBackend { out, width, height, raw_mode: false }
+    }
+}
+
+impl<W: Write> RenderBackend for AnsiBackend<W> {
+    fn init(&mut self) {
+        if self.raw_mode {
+            let _ = enable_raw_mode(); // Hides input keys, input processed without enter, etc.
+        }
+    }
+
+    // https://gist.github.com/ConnerWill/d4b6c776b509add763e17f9f113fd25b#colors--graphics-mode
+    fn write(&mut self, line: Line, column: Column, text: &str, style: Style) {
+        let _ = write!(self.out, "{ESC}[{line};{column}H");
+        if let Some(color) = style.color {
+            let _ = write!(self.out, "{ESC}[38;5;{color}m");
+        }
+        if style.underline {
+            let _ = write!(self.out, "{ESC}[4m");
+        }
+        let _ = write!(self.out, "{text}");
+        if style != Style::default() {
+            let _ = write!(self.out, "{ESC}[0m"); // Resets modes (color)
+        }
+    }
+
+    fn clear(&mut self) {
+        let _ = write!(self.out, "{ESC}[2J");
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        let code = if visible { 'h' } else { 'l' };
+        let _ = write!(self.out, "{ESC}[?25{code}");
+    }
+
+    fn set_focus_reporting(&mut self, enabled: bool) {
+        let code = if enabled { 'h' } else { 'l' };
+        let _ = write!(self.out, "{ESC}[?1004{code}");
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        if let Err(e) = self.out.flush() {
+            error!("[Backend] Could not flush the output: {e}");
+        }
+    }
+
+    fn size(&self) -> (Width, Height) {
+        (self.width, self.height)
+    }
+
+    fn refresh_size(&mut self) -> (Width, Height) {
+        // Only the real terminal changes size, a writer keeps the one it was given
+        if self.raw_mode && let Some((width, height)) = terminal_size::terminal_size() {
+            (self.width, self.height) = (width.0, height.0);
+        }
+        self.size()
+    }
+}
+
+/// Byte buffer to give to [`AnsiBackend::with_writer`].
+/// Clones share the same bytes: keep one to collect what the backend wrote.
+#[derive(Debug, Clone, Default)]
+pub struct SharedBuffer {
+    bytes: Rc<RefCell<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the bytes written since the previous call
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.bytes.borrow_mut())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps the drawn characters in a grid, so the output can be inspected without a terminal.
+/// Clones share the same grid: keep one to read what the [`Screen`](crate::screen::Screen) drew.
+#[derive(Debug, Clone)]
+pub struct MemoryBackend {
+    frame: Rc<RefCell<FrameBuffer>>,
+    cursor_visible: Rc<RefCell<bool>>,
+}
+
+impl MemoryBackend {
+    pub fn new(width: Width, height: Height) -> Self {
+        MemoryBackend {
+            frame: Rc::new(RefCell::new(FrameBuffer::new(width, height))),
+            cursor_visible: Rc::new(RefCell::new(true)),
+        }
+    }
+
+    pub fn get_cell(&self, line: Line, column: Column) -> Option<Cell> {
+        self.frame.borrow().get_cell(line, column)
+    }
+
+    /// Returns the characters of a line, without the trailing blanks
+    pub fn get_line(&self, line: Line) -> String {
+        self.frame.borrow().get_line(line)
+    }
+
+    /// Returns the whole grid as text, one line per row
+    pub fn contents(&self) -> String {
+        self.frame.borrow().contents()
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        *self.cursor_visible.borrow()
+    }
+}
+
+impl RenderBackend for MemoryBackend {
+    fn write(&mut self, line: Line, column: Column, text: &str, style: Style) {
+        self.frame.borrow_mut().write(line, column, text, style);
+    }
+
+    fn clear(&mut self) {
+        self.frame.borrow_mut().clear();
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        *self.cursor_visible.borrow_mut() = visible;
+    }
+
+    fn flush(&mut self) {}
+
+    fn size(&self) -> (Width, Height) {
+        self.frame.borrow().get_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ansi_backend_writes_styled_text_at_the_position() {
+        let buffer = SharedBuffer::new();
+        let mut backend = AnsiBackend::with_writer(buffer.clone(), 20, 5);
+        backend.write(2, 3, "ab", Style::colored(9));
+        backend.write(3, 1, "c", Style::default());
+        backend.set_cursor_visible(false);
+
+        let output = String::from_utf8(buffer.take()).unwrap();
+        assert_eq!(output, format!("{ESC}[2;3H{ESC}[38;5;9mab{ESC}[0m{ESC}[3;1Hc{ESC}[?25l"));
+    }
+
+    #[test]
+    fn memory_backend_keeps_the_drawn_cells() {
+        let mut backend = MemoryBackend::new(10, 3);
+        backend.write(2, 4, "hi", Style::underlined());
+        backend.set_cursor_visible(false);
+
+        assert_eq!(backend.get_line(2), "   hi");
+        assert_eq!(backend.get_cell(2, 5).map(|cell| cell.style), Some(Style::underlined()));
+        assert!(!backend.is_cursor_visible());
+
+        backend.clear();
+        assert_eq!(backend.contents(), "\n\n");
+    }
+}
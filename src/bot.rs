@@ -0,0 +1,117 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::settings::WallBehaviour;
+use crate::state::{DeathCause, GameState, TickOutcome};
+use crate::{Direction, Position};
+
+type Cell = (u16, u16);
+
+/// Computer player: takes the shortest path to the food, unless it leads into a space smaller
+/// than its body, in which case it heads for the biggest free area
+pub struct Bot;
+
+impl Bot {
+    /// Returns the direction to take on the next tick
+    pub fn next_direction(state: &GameState) -> Direction {
+        let snake = state.get_snake();
+        let current = snake.get_direction();
+        let head = *snake.get_head().get_position();
+        let food = state.get_food().position;
+        let solid_walls = state.get_walls() != WallBehaviour::Wrap; // Bouncing doesn't cross the wall either
+
+        // The tail moves away during the tick, so its cell is free
+        let mut positions = snake.get_positions();
+        positions.pop();
+        let mut blocked: HashSet<Cell> = positions.iter().map(|p| (p.line, p.column)).collect();
+        blocked.extend(state.get_hazards().iter().filter(|hazard| hazard.is_solid()).map(|hazard| (hazard.position.line, hazard.position.column)));
+        // Its paths don't go through portals, it keeps away from them
+        blocked.extend(state.get_portals().iter().map(|portal| (portal.position.line, portal.position.column)));
+        let length = positions.len() + 1;
+
+        // Moves that don't kill the snake right away
+        let moves: Vec<(Direction, Position)> = Self::neighbours(head, solid_walls)
+            .filter(|(direction, next)| *direction != current.opposite() && !blocked.contains(&(next.line, next.column)))
+            .collect();
+
+        if let Some(direction) = Self::path_to(head, food, &blocked, solid_walls)
+            && let Some((_, next)) = moves.iter().find(|(d, _)| *d == direction)
+            && Self::free_space(*next, &blocked, solid_walls, length) >= length {
+            return direction;
+        }
+
+        moves
+            .iter()
+            .max_by_key(|(_, next)| Self::free_space(*next, &blocked, solid_walls, length * 2))
+            .map(|(direction, _)| *direction)
+            .unwrap_or(current) // Every move is deadly: keeps going
+    }
+
+    /// Plays without drawing until the snake dies (returning why), fills the board or
+    /// `max_ticks` have passed
+    pub fn play(state: &mut GameState, max_ticks: u64) -> Option<DeathCause> {
+        while state.get_tick() < max_ticks {
+            match state.step(Some(Self::next_direction(state))) {
+                TickOutcome::Died(cause) => return Some(cause),
+                TickOutcome::Won => return None,
+                _ => (),
+            }
+        }
+        None
+    }
+
+    /// Returns the cells next to `position` with the direction to reach them
+    fn neighbours(position: Position, solid_walls: bool) -> impl Iterator<Item = (Direction, Position)> {
+        Direction::ALL.into_iter().filter_map(move |direction| {
+            let mut next = position;
+            next.move_towards(direction);
+            let wrapped = next.line.abs_diff(position.line) + next.column.abs_diff(position.column) != 1;
+            (!(solid_walls && wrapped)).then_some((direction, next))
+        })
+    }
+
+    /// Returns the first move of the shortest path from `start` to `target`, if there's one
+    fn path_to(start: Position, target: Position, blocked: &HashSet<Cell>, solid_walls: bool) -> Option<Direction> {
+        let mut first_moves: HashMap<Cell, Direction> = HashMap::new();
+        let mut queue: VecDeque<Position> = VecDeque::new();
+        for (direction, next) in Self::neighbours(start, solid_walls) {
+            let cell = (next.line, next.column);
+            if !blocked.contains(&cell) && !first_moves.contains_key(&cell) {
+                first_moves.insert(cell, direction);
+                queue.push_back(next);
+            }
+        }
+
+        while let Some(position) = queue.pop_front() {
+            let first_move = first_moves[&(position.line, position.column)];
+            if position == target {
+                return Some(first_move);
+            }
+            for (_, next) in Self::neighbours(position, solid_walls) {
+                let cell = (next.line, next.column);
+                if !blocked.contains(&cell) && !first_moves.contains_key(&cell) {
+                    first_moves.insert(cell, first_move);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Counts the free cells reachable from `start`, stopping at `limit`
+    fn free_space(start: Position, blocked: &HashSet<Cell>, solid_walls: bool, limit: usize) -> usize {
+        let mut visited: HashSet<Cell> = HashSet::from([(start.line, start.column)]);
+        let mut queue: VecDeque<Position> = VecDeque::from([start]);
+        while let Some(position) = queue.pop_front() {
+            if visited.len() >= limit {
+                break;
+            }
+            for (_, next) in Self::neighbours(position, solid_walls) {
+                let cell = (next.line, next.column);
+                if !blocked.contains(&cell) && visited.insert(cell) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        visited.len()
+    }
+}
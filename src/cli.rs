@@ -0,0 +1,241 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use log::LevelFilter;
+
+use crate::settings::{GameMode, Settings};
+
+pub const USAGE: &str = indoc::indoc! {"
+    Usage: snake [OPTIONS] [COMMAND]
+
+    Commands:
+      (none)             Opens the main menu
+      play               Starts a game right away
+      scores             Prints the high scores
+      replay <FILE>      Plays back a recorded game
+      export <FILE>      Converts a recorded game to an asciinema recording or an animated SVG
+      bot                Watches the computer play
+      help               Prints this message
+
+    Options:
+      --config <FILE>        Reads the settings from FILE instead of ~/.config/snake/config.toml
+      --log-file <FILE>      Writes the logs to FILE (snake.log if only --log-level is given)
+      --log-level <LEVEL>    off, error, warn, info, debug or trace (default: debug)
+
+    Play and bot options:
+      --mode <MODE>          classic, zen or hardcore
+      --speed <MS>           Milliseconds between two moves at the start
+      --seed <SEED>          Generates the game from SEED, to replay it exactly
+
+    Bot options:
+      --games <N>            Plays N games without drawing them and prints the results
+
+    Export options:
+      --output <FILE>        Where to write it, as SVG if FILE ends with .svg (default: FILE with the .cast extension)
+      --inputs <FILE>        Exports the game generated from --seed (and --mode, --speed) played with the
+                             inputs of FILE (e.g. 3L 10U 15R: tick and direction) instead of a replay
+"};
+
+/// Options of `play` and `bot` overriding the config file
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct PlayOptions {
+    pub mode: Option<GameMode>,
+    pub speed: Option<u64>,
+    pub seed: Option<u64>,
+}
+
+impl PlayOptions {
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(mode) = self.mode {
+            settings.mode = mode;
+        }
+        if let Some(speed) = self.speed {
+            settings.tick_rate = speed;
+        }
+    }
+
+    /// Applies the options again after reloading the config file as `saved`, except the settings
+    /// changed since (in the options menu)
+    pub fn apply_unchanged(&self, settings: &mut Settings, saved: &Settings) {
+        if let Some(mode) = self.mode
+            && settings.mode == saved.mode {
+            settings.mode = mode;
+        }
+        if let Some(speed) = self.speed
+            && settings.tick_rate == saved.tick_rate {
+            settings.tick_rate = speed;
+        }
+    }
+}
+
+/// Game to export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportSource {
+    Replay(PathBuf),
+    /// Game generated from the seed of `options`, played with the inputs of the file
+    Script { options: PlayOptions, inputs: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Menu,
+    Play(PlayOptions),
+    Scores,
+    Replay(PathBuf),
+    Export { source: ExportSource, output: Option<PathBuf> },
+    Bot { options: PlayOptions, games: Option<u32> },
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cli {
+    pub command: Command,
+    pub config: Option<PathBuf>,
+    pub log_file: Option<PathBuf>,
+    pub log_level: Option<LevelFilter>,
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value '{value}' for {flag}"))
+}
+
+impl Cli {
+    /// Parses the arguments, without the program name
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut command_name: Option<String> = None;
+        let mut positionals: Vec<String> = Vec::new();
+        let mut options = PlayOptions::default();
+        let mut games: Option<u32> = None;
+        let mut output: Option<PathBuf> = None;
+        let mut inputs: Option<PathBuf> = None;
+        let mut cli = Cli { command: Command::Menu, config: None, log_file: None, log_level: None };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                if command_name.is_none() {
+                    command_name = Some(arg);
+                } else {
+                    positionals.push(arg);
+                }
+                continue;
+            }
+
+            // Both "--flag value" and "--flag=value"
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), value.to_string()),
+                None if arg == "--help" => {
+                    cli.command = Command::Help;
+                    return Ok(cli);
+                }
+                None => {
+                    let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+                    (arg, value)
+                }
+            };
+            match flag.as_str() {
+                "--config" => cli.config = Some(PathBuf::from(value)),
+                "--log-file" => cli.log_file = Some(PathBuf::from(value)),
+                "--log-level" => cli.log_level = Some(parse_value(&flag, &value)?),
+                "--mode" => {
+                    options.mode = Some(GameMode::from_name(&value).ok_or_else(|| format!("unknown mode '{value}'"))?)
+                }
+                "--speed" => options.speed = Some(parse_value(&flag, &value)?),
+                "--seed" => options.seed = Some(parse_value(&flag, &value)?),
+                "--games" => games = Some(parse_value(&flag, &value)?),
+                "--output" => output = Some(PathBuf::from(value)),
+                "--inputs" => inputs = Some(PathBuf::from(value)),
+                _ => return Err(format!("unknown option {flag}")),
+            }
+        }
+
+        let takes_play_options = matches!(command_name.as_deref(), Some("play") | Some("bot"))
+            || (command_name.as_deref() == Some("export") && inputs.is_some());
+        if !takes_play_options && options != PlayOptions::default() {
+            return Err(String::from("--mode, --speed and --seed only work with play, bot and export --inputs"));
+        }
+        if command_name.as_deref() != Some("bot") && games.is_some() {
+            return Err(String::from("--games only works with bot"));
+        }
+        if command_name.as_deref() != Some("export") && (output.is_some() || inputs.is_some()) {
+            return Err(String::from("--output and --inputs only work with export"));
+        }
+
+        let expected_positionals = match command_name.as_deref() {
+            Some("replay") => 1,
+            Some("export") if inputs.is_none() => 1,
+            _ => 0,
+        };
+        if positionals.len() > expected_positionals {
+            return Err(format!("unexpected argument '{}'", positionals[expected_positionals]));
+        }
+
+        cli.command = match command_name.as_deref() {
+            None => Command::Menu,
+            Some("play") => Command::Play(options),
+            Some("scores") => Command::Scores,
+            Some("replay") => {
+                let file = positionals.pop().ok_or("replay needs a file")?;
+                Command::Replay(PathBuf::from(file))
+            }
+            Some("export") => {
+                let source = match inputs {
+                    Some(_) if options.seed.is_none() => return Err(String::from("export --inputs needs a --seed")),
+                    Some(inputs) => ExportSource::Script { options, inputs },
+                    None => ExportSource::Replay(PathBuf::from(positionals.pop().ok_or("export needs a replay file or --inputs")?)),
+                };
+                Command::Export { source, output }
+            }
+            Some("bot") => Command::Bot { options, games },
+            Some("help") => Command::Help,
+            Some(other) => return Err(format!("unknown command '{other}'")),
+        };
+        Ok(cli)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_command_opens_the_menu() {
+        assert_eq!(parse(&[]).unwrap().command, Command::Menu);
+    }
+
+    #[test]
+    fn play_takes_its_options_in_both_forms() {
+        let cli = parse(&["play", "--mode", "hardcore", "--speed=80", "--seed", "42", "--log-level", "info"]).unwrap();
+        let options = PlayOptions { mode: Some(GameMode::Hardcore), speed: Some(80), seed: Some(42) };
+        assert_eq!(cli.command, Command::Play(options));
+        assert_eq!(cli.log_level, Some(LevelFilter::Info));
+    }
+
+    #[test]
+    fn replay_needs_a_file() {
+        assert_eq!(parse(&["replay", "game.toml"]).unwrap().command, Command::Replay(PathBuf::from("game.toml")));
+        assert!(parse(&["replay"]).is_err());
+        assert!(parse(&["replay", "a.toml", "b.toml"]).is_err());
+    }
+
+    #[test]
+    fn options_only_work_with_their_commands() {
+        assert!(parse(&["scores", "--speed", "80"]).is_err());
+        assert!(parse(&["play", "--games", "3"]).is_err());
+        assert_eq!(parse(&["bot", "--games", "3"]).unwrap().command, Command::Bot { options: PlayOptions::default(), games: Some(3) });
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert!(parse(&["dance"]).is_err());
+        assert!(parse(&["play", "--mode", "easy"]).is_err());
+        assert!(parse(&["play", "--speed", "fast"]).is_err());
+        assert!(parse(&["play", "--speed"]).is_err());
+        assert!(parse(&["play", "--seed", "-1"]).is_err());
+        assert!(parse(&["--colour", "red"]).is_err());
+    }
+}
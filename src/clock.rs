@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// The tick never gets shorter than this, whatever the speed curve
+const MIN_TICK_MS: u64 = 20;
+
+/// How the tick gets shorter as the score grows
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SpeedCurve {
+    #[default]
+    Constant,
+    /// Removes `step` milliseconds for every food eaten
+    Linear { step: u64 },
+    /// Removes `step` milliseconds every `every` foods eaten
+    Stepped { every: u16, step: u64 },
+    /// Removes `step` milliseconds for every food eaten, until the tick reaches `min` milliseconds
+    Capped { step: u64, min: u64 },
+}
+
+impl SpeedCurve {
+    /// Cycles through the curves, with the parameters used by the options menu
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Constant => Self::Linear { step: 2 },
+            Self::Linear { .. } => Self::Stepped { every: 5, step: 10 },
+            Self::Stepped { .. } => Self::Capped { step: 2, min: 50 },
+            Self::Capped { .. } => Self::Constant,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            Self::Constant => Self::Capped { step: 2, min: 50 },
+            Self::Linear { .. } => Self::Constant,
+            Self::Stepped { .. } => Self::Linear { step: 2 },
+            Self::Capped { .. } => Self::Stepped { every: 5, step: 10 },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Constant => "Constant",
+            Self::Linear { .. } => "Linear",
+            Self::Stepped { .. } => "Stepped",
+            Self::Capped { .. } => "Capped",
+        }
+    }
+
+    /// Returns how long a tick lasts with the given score, starting from `base` milliseconds
+    pub fn tick_duration(&self, base: u64, score: u16) -> Duration {
+        let score = u64::from(score);
+        let reduction = match *self {
+            Self::Constant => 0,
+            Self::Linear { step } | Self::Capped { step, .. } => step.saturating_mul(score),
+            Self::Stepped { every, step } => step.saturating_mul(score / u64::from(every.max(1))),
+        };
+        Duration::from_millis(base.saturating_sub(reduction)).max(self.min_tick_duration())
+    }
+
+    /// The shortest a tick gets with this curve
+    pub fn min_tick_duration(&self) -> Duration {
+        match *self {
+            Self::Capped { min, .. } => Duration::from_millis(min.max(MIN_TICK_MS)),
+            _ => Duration::from_millis(MIN_TICK_MS),
+        }
+    }
+}
+
+/// Fixed timestep scheduler: ticks happen every `tick_duration`, regardless of how long
+/// the work between them (input polling, drawing) takes
+#[derive(Debug)]
+pub struct Clock {
+    tick_duration: Duration,
+    next_tick: Instant,
+}
+
+impl Clock {
+    pub fn new(tick_duration: Duration) -> Self {
+        Clock { tick_duration, next_tick: Instant::now() + tick_duration }
+    }
+
+    /// Takes effect from the tick after the one already scheduled
+    pub fn set_tick_duration(&mut self, tick_duration: Duration) {
+        self.tick_duration = tick_duration;
+    }
+
+    /// Time left before the next tick is due
+    pub fn remaining(&self) -> Duration {
+        self.next_tick.saturating_duration_since(Instant::now())
+    }
+
+    /// Returns true if the tick is due, scheduling the next one
+    pub fn tick(&mut self) -> bool {
+        let now = Instant::now();
+        if now < self.next_tick {
+            return false;
+        }
+
+        self.next_tick += self.tick_duration;
+        // Too far behind (e.g. the process was suspended): skips the lost ticks instead of rushing them
+        if self.next_tick < now {
+            self.next_tick = now + self.tick_duration;
+        }
+        true
+    }
+
+    /// Schedules the next tick a whole `tick_duration` from now, e.g. after a pause
+    pub fn reset(&mut self) {
+        self.next_tick = Instant::now() + self.tick_duration;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_curves_shorten_the_tick_as_the_score_grows() {
+        let base = 100;
+        assert_eq!(SpeedCurve::Constant.tick_duration(base, 30), Duration::from_millis(100));
+        assert_eq!(SpeedCurve::Linear { step: 2 }.tick_duration(base, 10), Duration::from_millis(80));
+        assert_eq!(SpeedCurve::Stepped { every: 5, step: 10 }.tick_duration(base, 14), Duration::from_millis(80));
+        assert_eq!(SpeedCurve::Capped { step: 5, min: 70 }.tick_duration(base, 4), Duration::from_millis(80));
+        assert_eq!(SpeedCurve::Capped { step: 5, min: 70 }.tick_duration(base, 40), Duration::from_millis(70));
+    }
+
+    #[test]
+    fn speed_curves_never_go_below_the_minimum_tick() {
+        assert_eq!(SpeedCurve::Linear { step: 10 }.tick_duration(100, 50), Duration::from_millis(MIN_TICK_MS));
+        assert_eq!(SpeedCurve::Capped { step: 10, min: 0 }.tick_duration(100, 50), Duration::from_millis(MIN_TICK_MS));
+        assert_eq!(SpeedCurve::Stepped { every: 0, step: 1 }.tick_duration(100, 3), Duration::from_millis(97));
+    }
+
+    #[test]
+    fn clock_ticks_once_the_duration_has_passed() {
+        let mut clock = Clock::new(Duration::from_millis(10));
+        assert!(!clock.tick());
+        std::thread::sleep(clock.remaining());
+        assert!(clock.tick());
+        assert!(!clock.tick());
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use log::info;
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::clock::SpeedCurve;
+use crate::settings::{Settings, MAX_HAZARDS, MAX_PORTAL_PAIRS};
+
+/// Tick rates accepted by the config file and the options menu, in ms
+pub const MIN_TICK_RATE: u64 = 10;
+pub const MAX_TICK_RATE: u64 = 2000;
+const MAX_START_LENGTH: u16 = 20;
+const MAX_INPUT_BUFFER: u8 = 8;
+const MIN_MULTIPLY_EVERY: u64 = 10;
+const MAX_HORIZONTAL_MARGIN: u16 = 100;
+/// The score line goes in the vertical margin above the border, the seed line in the one under it
+const MIN_VERTICAL_MARGIN: u16 = crate::HUD_LINES;
+const MAX_VERTICAL_MARGIN: u16 = 50;
+
+/// Set by `--config`, replaces the default location
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Makes every following load and save use `path` instead of the default location
+pub fn set_config_path(path: PathBuf) {
+    let _ = CONFIG_PATH_OVERRIDE.set(path);
+}
+
+/// Returns the `--config` path if given, otherwise `$XDG_CONFIG_HOME/snake/config.toml`
+/// (falling back to `~/.config` when the variable is not set)
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+        return Some(path.clone());
+    }
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("snake").join("config.toml"))
+}
+
+/// Returns `$XDG_DATA_HOME/snake`, falling back to `~/.local/share` when the variable is not set
+pub fn data_dir() -> Option<PathBuf> {
+    let data_dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local").join("share"),
+    };
+    Some(data_dir.join("snake"))
+}
+
+/// TOML integers are signed, so seeds (any u64) are saved as strings
+pub fn serialize_seed<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&seed.to_string())
+}
+
+pub fn deserialize_seed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    NoConfigDir,
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// A value that parsed but makes no sense: name of the setting and explanation
+    Invalid(PathBuf, &'static str, String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoConfigDir => write!(f, "could not find the config directory, set $XDG_CONFIG_HOME or $HOME"),
+            Self::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            Self::Parse(path, e) => write!(f, "{}: {e}", path.display()),
+            Self::Serialize(e) => write!(f, "could not write the settings: {e}"),
+            Self::Invalid(path, setting, reason) => write!(f, "{}: invalid `{setting}`: {reason}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Reads the config file. A missing file gives the default settings, unless it was given with `--config`.
+pub fn load() -> Result<Settings, ConfigError> {
+    let path = config_path().ok_or(ConfigError::NoConfigDir)?;
+    if !path.exists() && CONFIG_PATH_OVERRIDE.get().is_none() {
+        return Ok(Settings::default());
+    }
+    load_from(&path)
+}
+
+pub fn load_from(path: &Path) -> Result<Settings, ConfigError> {
+    let text = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+    let settings: Settings = toml::from_str(&text).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+    validate(&settings).map_err(|(setting, reason)| ConfigError::Invalid(path.to_path_buf(), setting, reason))?;
+    info!("[Config] Loaded from {}", path.display());
+    Ok(settings)
+}
+
+pub fn save(settings: &Settings) -> Result<(), ConfigError> {
+    let path = config_path().ok_or(ConfigError::NoConfigDir)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| ConfigError::Io(dir.to_path_buf(), e))?;
+    }
+    let text = toml::to_string_pretty(settings).map_err(ConfigError::Serialize)?;
+    fs::write(&path, text).map_err(|e| ConfigError::Io(path.clone(), e))?;
+    info!("[Config] Saved to {}", path.display());
+    Ok(())
+}
+
+/// Returns the name of the first invalid setting and why
+pub fn validate(settings: &Settings) -> Result<(), (&'static str, String)> {
+    if !(MIN_TICK_RATE..=MAX_TICK_RATE).contains(&settings.tick_rate) {
+        return Err(("tick_rate", format!("{} ms is outside of {MIN_TICK_RATE}..={MAX_TICK_RATE}", settings.tick_rate)));
+    }
+    match settings.speed_curve {
+        SpeedCurve::Stepped { every: 0, .. } => {
+            return Err(("speed_curve.every", String::from("must be at least 1")));
+        }
+        SpeedCurve::Capped { min, .. } if min > settings.tick_rate => {
+            return Err(("speed_curve.min", format!("{min} ms is slower than tick_rate ({} ms)", settings.tick_rate)));
+        }
+        _ => (),
+    }
+
+    if settings.margins.horizontal > MAX_HORIZONTAL_MARGIN {
+        return Err(("margins.horizontal", format!("must be at most {MAX_HORIZONTAL_MARGIN}")));
+    }
+    if !(MIN_VERTICAL_MARGIN..=MAX_VERTICAL_MARGIN).contains(&settings.margins.vertical) {
+        return Err(("margins.vertical", format!("must be within {MIN_VERTICAL_MARGIN}..={MAX_VERTICAL_MARGIN}")));
+    }
+    if settings.hazards_count > MAX_HAZARDS {
+        return Err(("hazards_count", format!("must be at most {MAX_HAZARDS}")));
+    }
+    for (name, rules) in settings.hazard_rules.named() {
+        let moving = u16::from(rules.patrol) + u16::from(rules.drift) + u16::from(rules.blink);
+        if moving > u16::from(MAX_HAZARDS) {
+            return Err((name, format!("patrol, drift and blink add up to {moving}, more than {MAX_HAZARDS}")));
+        }
+        if rules.multiply_every != 0 && rules.multiply_every < MIN_MULTIPLY_EVERY {
+            return Err((name, format!("multiply_every must be 0 (never) or at least {MIN_MULTIPLY_EVERY}")));
+        }
+    }
+    if settings.portal_pairs > MAX_PORTAL_PAIRS {
+        return Err(("portal_pairs", format!("must be at most {MAX_PORTAL_PAIRS}")));
+    }
+    if !(1..=MAX_START_LENGTH).contains(&settings.start_length) {
+        return Err(("start_length", format!("must be between 1 and {MAX_START_LENGTH}")));
+    }
+    if !(1..=MAX_INPUT_BUFFER).contains(&settings.input_buffer) {
+        return Err(("input_buffer", format!("must be between 1 and {MAX_INPUT_BUFFER}")));
+    }
+
+    for (name, weights) in settings.food_weights.named() {
+        if weights.total() == 0 {
+            return Err((name, String::from("at least one kind of food needs a weight")));
+        }
+    }
+
+    for (name, color) in settings.colors.named() {
+        if let Some(color) = color && color > 255 {
+            return Err((name, format!("{color} is not a 256 colors code (0..=255)")));
+        }
+    }
+
+    for (name, glyph) in settings.glyphs.named() {
+        if glyph.is_control() || glyph.is_whitespace() {
+            return Err((name, format!("{glyph:?} is not a visible character")));
+        }
+    }
+
+    let mut bound: HashMap<char, &'static str> = HashMap::new();
+    for (name, keys) in settings.key_bindings.named() {
+        for key in keys {
+            if key.is_control() {
+                return Err((name, format!("{key:?} can't be typed")));
+            }
+            if let Some(other) = bound.insert(*key, name) {
+                return Err((name, format!("'{key}' is also bound to `{other}`")));
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use crate::{Column, Line, Position, ESC};
+use crate::{Column, Line, Position};
+
+/// Tracks where the [`Screen`](crate::screen::Screen) writes next, the backend does the actual output
 
 #[derive(Default, Debug)]
 pub struct Cursor {
@@ -36,7 +38,6 @@ impl Cursor {
     }
 
     pub fn jump(&mut self, line: Line, column: Column) {
-        print!("{ESC}[{line};{column}H");
         self.position.line = line;
         self.position.column = column;
     }
@@ -62,7 +63,6 @@ impl Cursor {
     }
 
     pub fn move_cursor (&mut self, movement: CursorMovement, offset: u16) {
-        print!("{ESC}[{offset}{}", movement.character());
         match movement {
             CursorMovement::Left => self.position.column -= if self.position.column > 0 { offset } else { 0 },
             CursorMovement::Right => self.position.column += offset,
@@ -72,12 +72,10 @@ impl Cursor {
     }
 
     pub fn hide(&mut self) {
-        print!("{ESC}[?25l");
         self.hidden = true;
     }
 
     pub fn show(&mut self) {
-        print!("{ESC}[?25h");
         self.hidden = false;
     }
 
@@ -92,14 +90,3 @@ pub enum CursorMovement {
     Left,
     Right
 }
-
-impl CursorMovement {
-    pub fn character(&self) -> String {
-        match self {
-            CursorMovement::Up => String::from("A"),
-            CursorMovement::Down => String::from("B"),
-            CursorMovement::Left => String::from("D"),
-            CursorMovement::Right => String::from("C")
-        }
-    }
-}
@@ -1,8 +1,12 @@
-use std::iter;
-
 use log::info;
 
-use crate::{game::{MapItem, MapItemType}, screen::Screen, snake::Snake, Border, Column, Line, Direction, Position, GREEN, RED};
+use crate::backend::Style;
+use crate::food::FoodKind;
+use crate::powerup::EffectStack;
+use crate::{settings::{Glyphs, Palette}, state::{GameState, MapItem, MapItemType, TickOutcome}, screen::Screen, snake::Snake, Border, Column, Line, Direction, Position};
+
+/// Columns from the left of the border to where the power-ups in effect can be drawn
+const EFFECTS_OFFSET: u16 = 16;
 
 pub struct Drawer;
 impl Drawer {
@@ -11,10 +15,8 @@ impl Drawer {
     }
 
     pub fn draw_text(screen: &mut Screen, text: &str, position: Position) {
-        let mut column = position.column;
-        for char in text.chars() {
+        for (column, char) in (position.column..).zip(text.chars()) {
             screen.draw(position.line, column, char);
-            column += 1;
         }
     }
 
@@ -28,14 +30,17 @@ impl Drawer {
         info!("======== End Deleting snake ======");
     }
 
-    pub fn render_map_item (screen: &mut Screen, item: &MapItem) {
+    pub fn render_map_item (screen: &mut Screen, item: &MapItem, palette: &Palette, glyphs: &Glyphs) {
         let (icon, color) = match item.item_type {
-            MapItemType::Food => ('✿', 26),
-            MapItemType::Hazard => ('☠', RED),
+            MapItemType::Food(kind) => (kind.glyph(glyphs), kind.color(palette)),
+            MapItemType::Hazard(behaviour) if behaviour.is_warning() => (glyphs.warning, palette.hazard),
+            MapItemType::Hazard(behaviour) if !behaviour.is_solid() => return,
+            MapItemType::Hazard(_) => (glyphs.hazard, palette.hazard),
+            MapItemType::PowerUp(power_up) => (power_up.glyph(glyphs), palette.power_up),
+            MapItemType::Portal(_) => (glyphs.portal, palette.portal),
         };
 
-        Screen::draw_colored(
-            screen,
+        screen.draw_colored(
             item.position.line,
             item.position.column,
             icon,
@@ -43,53 +48,125 @@ impl Drawer {
         );
     }
 
-    pub fn draw_snake(screen: &mut Screen, snake: &Snake) {
+    /// Picks the glyph of a body node from the directions towards its neighbours
+    fn body_glyph(glyphs: &Glyphs, towards_previous: Option<Direction>, towards_next: Option<Direction>) -> char {
+        use Direction::{Down, Left, Right, Up};
+        match (towards_previous, towards_next) {
+            (Some(Left | Right), Some(Left | Right) | None) | (None, Some(Left | Right)) => glyphs.horizontal,
+            (Some(Up | Down), Some(Up | Down) | None) | (None, Some(Up | Down)) => glyphs.vertical,
+            (Some(Down), Some(Right)) | (Some(Right), Some(Down)) => glyphs.top_left,
+            (Some(Down), Some(Left)) | (Some(Left), Some(Down)) => glyphs.top_right,
+            (Some(Up), Some(Right)) | (Some(Right), Some(Up)) => glyphs.bottom_left,
+            (Some(Up), Some(Left)) | (Some(Left), Some(Up)) => glyphs.bottom_right,
+            (None, None) => glyphs.body,
+        }
+    }
+
+    pub fn draw_snake(screen: &mut Screen, snake: &Snake, palette: &Palette, glyphs: &Glyphs) {
         info!("======== Start Drawing snake ======");
-        let head = snake.get_head();
-        let head_position = head.get_position();
-        let mut iterator = snake.get_list().iter().peekable();
-        let _ = iterator.next(); // Skips head
-        screen.draw_colored(head_position.line, head_position.column, '◉', GREEN);
+        let head_position = snake.get_head().get_position();
+        screen.draw_colored(head_position.line, head_position.column, glyphs.head, palette.snake);
 
-        while let Some(node) = iterator.next() {
+        for (index, node) in snake.get_list().iter().enumerate().skip(1) {
             let position = node.get_position();
-            if let Some(direction) = node.get_direction() {
-                let mut character = match direction {
-                    Direction::Right | Direction::Left => '━',
-                    Direction::Up | Direction::Down => '┃',
-                };
-
-                // I honestly don't understand why this works. The characters don't match with the directions, but
-                // somehow they are correctly rendered
-                if let Some(next_node) = iterator.peek() {
-                    if let Some(following_direction) = next_node.get_direction() {
-                        if direction != following_direction {
-                            if (matches!(direction, Direction::Down) && matches!(following_direction, Direction::Left))
-                                || (matches!(direction, Direction::Right) && matches!(following_direction, Direction::Up)){
-                                    character = '┏';
-                                } else if (matches!(direction, Direction::Down) && matches!(following_direction, Direction::Right))
-                                    || (matches!(direction, Direction::Left) && matches!(following_direction, Direction::Up)) {
-                                        character = '┓';
-                                    } else if (matches!(direction, Direction::Up) && matches!(following_direction, Direction::Left))
-                                        || (matches!(direction, Direction::Right) && matches!(following_direction, Direction::Down)) {
-                                            character = '┗';
-                                        } else if (matches!(direction, Direction::Up) && matches!(following_direction, Direction::Right))
-                                            || (matches!(direction, Direction::Left) && matches!(following_direction, Direction::Down)){
-                                                character = '┛';
-                                        }
-                                        info!("[NODE DIRECTION]: {:?}, [FOLLOWING NODE DIRECTION]: {:?}, [CHARACTER]: {character}", direction, following_direction);
-                        }
-                    }
-                    screen.draw_colored(position.line, position.column, character, GREEN);
-                }
-            } else {
-                screen.draw_colored(position.line, position.column, '⬤', GREEN);
-            }
-            info!("Drawed at {:?}", position);
+            let (towards_previous, towards_next) = snake.get_neighbour_directions(index);
+            let character = Self::body_glyph(glyphs, towards_previous, towards_next);
+            screen.draw_colored(position.line, position.column, character, palette.snake);
         }
         info!("======== End Drawing snake ======");
     }
 
+    /// Erases what can change during a tick: the snake, the hazards and the food
+    pub fn erase_moving(screen: &mut Screen, state: &GameState) {
+        Self::delete_snake(screen, state.get_snake());
+        let food = state.get_food().position;
+        Self::eat_cell(screen, food.line, food.column);
+        if let Some(power_up) = state.get_power_up() {
+            Self::eat_cell(screen, power_up.position.line, power_up.position.column);
+        }
+        for hazard in state.get_hazards() {
+            Self::eat_cell(screen, hazard.position.line, hazard.position.column);
+        }
+    }
+
+    /// Draws what changed during a tick ending with `outcome`, after [`Drawer::erase_moving`]
+    pub fn draw_moving(screen: &mut Screen, state: &GameState, outcome: TickOutcome, palette: &Palette, glyphs: &Glyphs) {
+        // The snake hides the portal it comes out of, until its tail leaves it
+        for item in state.get_portals().iter().chain(state.get_hazards()) {
+            Self::render_map_item(screen, item, palette, glyphs);
+        }
+        Self::draw_snake(screen, state.get_snake(), palette, glyphs);
+        Self::render_map_item(screen, state.get_food(), palette, glyphs); // Expiring fruits move on their own
+        if let Some(power_up) = state.get_power_up() {
+            Self::render_map_item(screen, power_up, palette, glyphs);
+        }
+        Self::draw_effects(screen, state);
+        if outcome == TickOutcome::AteFood {
+            Self::draw_score(screen, &state.get_border(), state.get_score());
+        }
+    }
+
+    /// Draws the whole arena from a game state: borders, score, seed, food, hazards and snake
+    pub fn draw_game(screen: &mut Screen, state: &GameState, palette: &Palette, glyphs: &Glyphs) {
+        let border = state.get_border();
+        Self::draw_borders(screen, &border);
+        Self::draw_score(screen, &border, state.get_score());
+        Self::draw_seed(screen, &border, state.get_seed());
+        Self::render_map_item(screen, state.get_food(), palette, glyphs);
+        for item in state.get_portals().iter().chain(state.get_hazards()) {
+            Self::render_map_item(screen, item, palette, glyphs);
+        }
+        if let Some(power_up) = state.get_power_up() {
+            Self::render_map_item(screen, power_up, palette, glyphs);
+        }
+        Self::draw_snake(screen, state.get_snake(), palette, glyphs);
+        Self::draw_effects(screen, state);
+        screen.flush();
+    }
+
+    /// Draws the power-ups in effect with the seconds they have left at the current speed, then the countdown of an
+    /// expiring fruit, right-aligned on the line of the score: "Ghost 4s  Shield  Expiring 3s"
+    pub fn draw_effects(screen: &mut Screen, state: &GameState) {
+        let border = state.get_border();
+        let mut effects: Vec<String> = state
+            .get_effects()
+            .get_effects()
+            .iter()
+            .map(|effect| match EffectStack::ticks_left(effect, state.get_tick()) {
+                Some(ticks) => format!("{} {}s", effect.power_up.name(), state.seconds_for(ticks)),
+                None => effect.power_up.name().to_string(),
+            })
+            .collect();
+        if let Some(ticks) = state.get_food_countdown() {
+            effects.push(format!("{} {}s", FoodKind::Expiring.name(), state.seconds_for(ticks)));
+        }
+        // Leaves room for the score on the left
+        let start = border.start_col + EFFECTS_OFFSET;
+        let width = usize::from(border.end_col.saturating_sub(start + 1));
+        let text = format!("{:>width$}", effects.join("  "));
+        let skip = text.chars().count().saturating_sub(width); // Cuts the oldest ones when it's too long
+        let text: String = text.chars().skip(skip).collect();
+        if let Some(line) = Self::score_line(&border) {
+            Self::draw_text(screen, &text, Position::new(line, start));
+        }
+    }
+
+    /// Line above the border, None when the border starts on the first line of the terminal
+    fn score_line(border: &Border) -> Option<Line> {
+        border.start_line.checked_sub(1).filter(|line| *line > 0)
+    }
+
+    pub fn draw_score(screen: &mut Screen, border: &Border, score: u16) {
+        if let Some(line) = Self::score_line(border) {
+            Self::draw_text(screen, format!("Score: {}", score).as_str(), Position::new(line, border.start_col + 2));
+        }
+    }
+
+    /// Draws the seed under the arena, to replay the game with `--seed`
+    pub fn draw_seed(screen: &mut Screen, border: &Border, seed: u64) {
+        Self::draw_text(screen, format!("Seed: {seed}").as_str(), Position::new(border.end_line + 1, border.start_col + 2));
+    }
+
     pub fn draw_borders(screen: &mut Screen, border: &Border) {
         Self::draw_rectangle(
             screen, 
@@ -102,8 +179,22 @@ impl Drawer {
         );
     }
 
+    /// Clears a box of the given size in the middle of the screen and draws its border,
+    /// returns its top left corner
+    pub fn draw_overlay(screen: &mut Screen, width: u16, height: u16) -> Position {
+        let (screen_width, screen_height) = screen.get_terminal_size();
+        let start = Position::new(screen_height.saturating_sub(height) / 2 + 1, screen_width.saturating_sub(width) / 2 + 1);
+
+        // Hides what's behind the box
+        let blank = " ".repeat(usize::from(width + 1));
+        for line in start.line..=start.line + height {
+            screen.draw_formatted_text(line, start.column, blank.as_str(), Style::default());
+        }
+        Self::draw_rectangle(screen, start, width, height);
+        start
+    }
+
     pub fn draw_rectangle(screen: &mut Screen, start: Position, width: u16, height: u16) {
-        let cursor = &mut screen.cursor;
         if width == 0 || height == 0 {
             return;
         }
@@ -112,35 +203,81 @@ impl Drawer {
         let end_line = start_line + height;
         let start_col = start.column;
         let end_col = start_col + width;
-        cursor.jump(start_line, start_col);
+        screen.cursor.jump(start_line, start_col);
 
         // Renders top line
         for i in start_col..=end_col {
             match i {
-                start if start == start_col => print!("╭"),
-                num if num == end_col => print!("╮"),
-                _ => print!("─"),
+                start if start == start_col => screen.print("╭"),
+                num if num == end_col => screen.print("╮"),
+                _ => screen.print("─"),
             }
         }
-        cursor.down(1);
+        screen.cursor.down(1);
 
         // Renders left and side lines
         for _ in start_line..end_line - 1 {
-            cursor.jump_to_col(start_col);
-            print!("│");
-            cursor.jump_to_col(end_col);
-            print!("│");
-            cursor.down(1);
+            screen.cursor.jump_to_col(start_col);
+            screen.print("│");
+            screen.cursor.jump_to_col(end_col);
+            screen.print("│");
+            screen.cursor.down(1);
         }
 
         // Renders bottom line
-        cursor.jump_to_col(start_col);
+        screen.cursor.jump_to_col(start_col);
         for i in start_col..=end_col {
             match i {
-                start if start == start_col => print!("╰"),
-                num if num == end_col => print!("╯"),
-                _ => print!("─"),
+                start if start == start_col => screen.print("╰"),
+                num if num == end_col => screen.print("╯"),
+                _ => screen.print("─"),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+    use crate::settings::Theme;
+    use crate::snake::SnakeNode;
+
+    fn screen(width: u16, height: u16) -> (Screen, MemoryBackend) {
+        let backend = MemoryBackend::new(width, height);
+        (Screen::with_backend(Box::new(backend.clone())), backend)
+    }
+
+    #[test]
+    fn draw_rectangle_draws_rounded_corners() {
+        let (mut screen, backend) = screen(10, 5);
+        Drawer::draw_rectangle(&mut screen, Position::new(2, 3), 4, 2);
+        screen.flush();
+
+        assert_eq!(backend.contents(), "\n  ╭───╮\n  │   │\n  ╰───╯\n");
+    }
+
+    #[test]
+    fn draw_snake_bends_the_body() {
+        let (mut screen, backend) = screen(8, 4);
+        let mut snake = Snake::new(Direction::Right, SnakeNode::new(Position::new(2, 5)), None);
+        snake.add_tail_at(Position::new(2, 4));
+        snake.add_tail_at(Position::new(3, 4));
+        let palette = Theme::Classic.palette();
+        Drawer::draw_snake(&mut screen, &snake, &palette, &Glyphs::default());
+        screen.flush();
+
+        assert_eq!(backend.contents(), "\n   ┏◉\n   ┃\n");
+        assert_eq!(backend.get_cell(2, 5).unwrap().style.color, Some(palette.snake));
+    }
+
+    #[test]
+    fn draw_score_skips_a_border_on_the_first_line() {
+        let (mut screen, backend) = screen(20, 4);
+        Drawer::draw_score(&mut screen, &Border::new(1, 12, 1, 4), 7);
+        Drawer::draw_score(&mut screen, &Border::new(1, 12, 2, 4), 7);
+        screen.flush();
+
+        assert_eq!(backend.get_line(1), "  Score: 7");
+    }
+}
@@ -0,0 +1,207 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{GameMode, Glyphs, Palette};
+
+/// Ticks an expiring fruit stays before going somewhere else
+pub const EXPIRING_TICKS: u64 = 60;
+/// Ticks the speed and slow fruits change the speed for
+pub const SPEED_EFFECT_TICKS: u64 = 50;
+/// Nodes lost when eating rotten food, the head always stays
+pub const ROTTEN_SHRINK: usize = 3;
+
+/// What the snake can eat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FoodKind {
+    #[default]
+    Regular,
+    Golden, // Worth more
+    Rotten, // Shrinks the snake
+    Speed, // Speeds the game up for a while
+    Slow, // Slows the game down for a while
+    Expiring, // Worth more, goes away after a countdown
+}
+
+impl FoodKind {
+    pub const ALL: [FoodKind; 6] = [Self::Regular, Self::Golden, Self::Rotten, Self::Speed, Self::Slow, Self::Expiring];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Regular => "Regular",
+            Self::Golden => "Golden",
+            Self::Rotten => "Rotten",
+            Self::Speed => "Speed",
+            Self::Slow => "Slow",
+            Self::Expiring => "Expiring",
+        }
+    }
+
+    pub fn points(&self) -> u16 {
+        match self {
+            Self::Regular | Self::Slow => 1,
+            Self::Speed => 2,
+            Self::Expiring => 3,
+            Self::Golden => 5,
+            Self::Rotten => 0,
+        }
+    }
+
+    /// Returns true if eating it makes the snake grow, false if it shrinks
+    pub fn grows(&self) -> bool {
+        *self != Self::Rotten
+    }
+
+    /// Tick duration while its effect lasts, in percent of the normal one
+    pub fn tick_percent(&self) -> Option<u32> {
+        match self {
+            Self::Speed => Some(70),
+            Self::Slow => Some(150),
+            _ => None,
+        }
+    }
+
+    pub fn glyph(&self, glyphs: &Glyphs) -> char {
+        match self {
+            Self::Regular => glyphs.food,
+            Self::Golden => glyphs.golden,
+            Self::Rotten => glyphs.rotten,
+            Self::Speed => glyphs.speed,
+            Self::Slow => glyphs.slow,
+            Self::Expiring => glyphs.expiring,
+        }
+    }
+
+    pub fn color(&self, palette: &Palette) -> u16 {
+        match self {
+            Self::Regular => palette.food,
+            Self::Golden => palette.golden,
+            Self::Rotten => palette.rotten,
+            Self::Speed => palette.speed,
+            Self::Slow => palette.slow,
+            Self::Expiring => palette.expiring,
+        }
+    }
+}
+
+/// How often each kind of food spawns, relative to the others (0: never)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FoodWeights {
+    pub regular: u16,
+    pub golden: u16,
+    pub rotten: u16,
+    pub speed: u16,
+    pub slow: u16,
+    pub expiring: u16,
+}
+
+impl FoodWeights {
+    pub fn get(&self, kind: FoodKind) -> u16 {
+        match kind {
+            FoodKind::Regular => self.regular,
+            FoodKind::Golden => self.golden,
+            FoodKind::Rotten => self.rotten,
+            FoodKind::Speed => self.speed,
+            FoodKind::Slow => self.slow,
+            FoodKind::Expiring => self.expiring,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        FoodKind::ALL.iter().map(|kind| u32::from(self.get(*kind))).sum()
+    }
+
+    /// Picks a kind of food following the weights, regular food if they're all 0
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> FoodKind {
+        let total = self.total();
+        if total == 0 {
+            return FoodKind::Regular;
+        }
+        let mut roll = rng.random_range(0..total);
+        for kind in FoodKind::ALL {
+            let weight = u32::from(self.get(kind));
+            if roll < weight {
+                return kind;
+            }
+            roll -= weight;
+        }
+        FoodKind::Regular
+    }
+}
+
+/// The food weights of every mode, `[food_weights.<mode>]` in the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "PartialModeFoodWeights")]
+pub struct ModeFoodWeights {
+    pub classic: FoodWeights,
+    pub zen: FoodWeights,
+    pub hardcore: FoodWeights,
+}
+
+/// [`ModeFoodWeights`] as written in the config file, the weights left out keep the mode defaults
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PartialModeFoodWeights {
+    classic: PartialFoodWeights,
+    zen: PartialFoodWeights,
+    hardcore: PartialFoodWeights,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PartialFoodWeights {
+    regular: Option<u16>,
+    golden: Option<u16>,
+    rotten: Option<u16>,
+    speed: Option<u16>,
+    slow: Option<u16>,
+    expiring: Option<u16>,
+}
+
+impl PartialFoodWeights {
+    fn or(self, defaults: FoodWeights) -> FoodWeights {
+        FoodWeights {
+            regular: self.regular.unwrap_or(defaults.regular),
+            golden: self.golden.unwrap_or(defaults.golden),
+            rotten: self.rotten.unwrap_or(defaults.rotten),
+            speed: self.speed.unwrap_or(defaults.speed),
+            slow: self.slow.unwrap_or(defaults.slow),
+            expiring: self.expiring.unwrap_or(defaults.expiring),
+        }
+    }
+}
+
+impl From<PartialModeFoodWeights> for ModeFoodWeights {
+    fn from(partial: PartialModeFoodWeights) -> Self {
+        let defaults = Self::default();
+        ModeFoodWeights {
+            classic: partial.classic.or(defaults.classic),
+            zen: partial.zen.or(defaults.zen),
+            hardcore: partial.hardcore.or(defaults.hardcore),
+        }
+    }
+}
+
+impl Default for ModeFoodWeights {
+    fn default() -> Self {
+        ModeFoodWeights {
+            classic: FoodWeights { regular: 70, golden: 10, rotten: 5, speed: 5, slow: 5, expiring: 5 },
+            zen: FoodWeights { regular: 80, golden: 10, rotten: 0, speed: 0, slow: 10, expiring: 0 },
+            hardcore: FoodWeights { regular: 50, golden: 10, rotten: 15, speed: 10, slow: 0, expiring: 15 },
+        }
+    }
+}
+
+impl ModeFoodWeights {
+    pub fn get(&self, mode: GameMode) -> FoodWeights {
+        match mode {
+            GameMode::Classic => self.classic,
+            GameMode::Zen => self.zen,
+            GameMode::Hardcore => self.hardcore,
+        }
+    }
+
+    /// Returns the weights of every mode with the name of their setting
+    pub fn named(&self) -> [(&'static str, FoodWeights); 3] {
+        [("food_weights.classic", self.classic), ("food_weights.zen", self.zen), ("food_weights.hardcore", self.hardcore)]
+    }
+}
@@ -0,0 +1,160 @@
+use crate::backend::Style;
+use crate::{Column, Height, Line, Width};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub character: char,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell { character: ' ', style: Style::default() }
+    }
+}
+
+/// Consecutive cells of a line sharing the same style, sent to the backend with a single write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub line: Line,
+    pub column: Column,
+    pub text: String,
+    pub style: Style,
+}
+
+/// Grid of styled cells covering the whole terminal.
+/// Lines and columns are 1-based, like the ANSI cursor positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameBuffer {
+    width: Width,
+    height: Height,
+    cells: Vec<Cell>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: Width, height: Height) -> Self {
+        FrameBuffer {
+            width,
+            height,
+            cells: vec![Cell::default(); usize::from(width) * usize::from(height)],
+        }
+    }
+
+    /// Returns width and height as tuple
+    pub fn get_size(&self) -> (Width, Height) {
+        (self.width, self.height)
+    }
+
+    fn index(&self, line: Line, column: Column) -> Option<usize> {
+        // Like the terminal, 0 is treated as the first line/column
+        let (line, column) = (line.max(1) - 1, column.max(1) - 1);
+        if line >= self.height || column >= self.width {
+            return None;
+        }
+        Some(usize::from(line) * usize::from(self.width) + usize::from(column))
+    }
+
+    pub fn get_cell(&self, line: Line, column: Column) -> Option<Cell> {
+        self.index(line, column).map(|index| self.cells[index])
+    }
+
+    pub fn set_cell(&mut self, line: Line, column: Column, cell: Cell) {
+        if let Some(index) = self.index(line, column) {
+            self.cells[index] = cell;
+        }
+    }
+
+    /// Writes `text` from the given position to the right, dropping what falls outside of the grid
+    pub fn write(&mut self, line: Line, column: Column, text: &str, style: Style) {
+        for (column, character) in (column.max(1)..).zip(text.chars()) {
+            self.set_cell(line, column, Cell { character, style });
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(Cell::default());
+    }
+
+    /// Returns the characters of a line, without the trailing blanks
+    pub fn get_line(&self, line: Line) -> String {
+        let text: String = (1..=self.width)
+            .filter_map(|column| self.get_cell(line, column))
+            .map(|cell| cell.character)
+            .collect();
+        text.trim_end().to_string()
+    }
+
+    /// Returns the whole grid as text, one line per row
+    pub fn contents(&self) -> String {
+        (1..=self.height)
+            .map(|line| self.get_line(line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Returns the runs of cells that differ from `previous`, which must have the same size
+    pub fn diff(&self, previous: &FrameBuffer) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        for line in 1..=self.height {
+            let mut current: Option<Run> = None;
+            for column in 1..=self.width {
+                let cell = self.get_cell(line, column).unwrap_or_default();
+                if previous.get_cell(line, column) == Some(cell) {
+                    runs.extend(current.take());
+                    continue;
+                }
+
+                match current.as_mut() {
+                    Some(run) if run.style == cell.style => run.text.push(cell.character),
+                    _ => {
+                        runs.extend(current.take());
+                        current = Some(Run { line, column, text: cell.character.to_string(), style: cell.style });
+                    }
+                }
+            }
+            runs.extend(current);
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_groups_changed_cells_by_style() {
+        let previous = FrameBuffer::new(10, 2);
+        let mut frame = previous.clone();
+        frame.write(1, 2, "ab", Style::default());
+        frame.write(1, 4, "c", Style::colored(2));
+        frame.write(2, 1, "d", Style::default());
+
+        assert_eq!(frame.diff(&previous), vec![
+            Run { line: 1, column: 2, text: String::from("ab"), style: Style::default() },
+            Run { line: 1, column: 4, text: String::from("c"), style: Style::colored(2) },
+            Run { line: 2, column: 1, text: String::from("d"), style: Style::default() },
+        ]);
+    }
+
+    #[test]
+    fn diff_splits_runs_around_unchanged_cells() {
+        let mut previous = FrameBuffer::new(10, 1);
+        previous.write(1, 1, "abcde", Style::default());
+        let mut frame = previous.clone();
+        frame.write(1, 1, "xbcye", Style::default());
+
+        let columns: Vec<Column> = frame.diff(&previous).iter().map(|run| run.column).collect();
+        assert_eq!(columns, vec![1, 4]);
+        assert!(frame.diff(&frame).is_empty());
+    }
+
+    #[test]
+    fn write_drops_text_outside_of_the_grid() {
+        let mut frame = FrameBuffer::new(4, 1);
+        frame.write(1, 3, "abc", Style::default());
+        frame.write(2, 1, "d", Style::default());
+
+        assert_eq!(frame.contents(), "  ab");
+    }
+}
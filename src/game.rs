@@ -1,226 +1,372 @@
-use std::{thread::sleep, time::Duration};
-use crossterm::event::{Event, KeyCode};
-use log::{debug, info};
-use rand::Rng;
-use crate::{Border, Direction, Position};
+use std::time::{Duration, Instant};
+use crossterm::event::Event;
+use log::{debug, error, info};
+use crate::{Border, Direction};
+use crate::bot::Bot;
+use crate::cli::PlayOptions;
+use crate::clock::Clock;
 use crate::screen::Screen;
 use crate::drawing::Drawer;
-use crate::snake::{Snake, SnakeNode};
-
-fn random_position(border: &Border) -> Position {
-    let mut rng = rand::rng();
-    let border: Border = *border;
-    Position::new(
-        rng.random_range(border.start_line + 2..border.end_line-1), 
-        rng.random_range(border.start_col + 2..border.end_col-1)
-    )
-}
-
-pub enum MapItemType {
-    Food,
-    Hazard,
-}
-
-#[allow(dead_code)]
-pub struct MapItem {
-    pub item_type: MapItemType,
-    pub position: Position,
-}
-
-impl MapItem {
-    pub fn new(item_type: MapItemType, position: Position) -> Self {
-        MapItem {
-            item_type,
-            position,
-        }
-    }
-
-    fn set_random_position(&mut self, border: &Border, invalid_positions: &Vec<Position>) {
-        let mut position = random_position(border);
-
-        let mut invalid: bool = true;
-        while invalid {
-            invalid = false;
-            for invalid_position in invalid_positions {
-                if position == *invalid_position {
-                    position = random_position(border);
-                    invalid = true;
-                }
-            }
-        }
-        self.position = position;
-    }
+use crate::gameover::{GameOverChoice, GameOverMenu, GameStats};
+use crate::input::DirectionQueue;
+use crate::leaderboard::NamePrompt;
+use crate::options::OptionsMenu;
+use crate::pause::{PauseChoice, PauseMenu};
+use crate::replay::Replay;
+use crate::scores::{today, HighScores, ScoreEntry};
+use crate::settings::{KeyBindings, Settings};
+use crate::state::{GameState, TickOutcome};
 
+/// What the player asked for while playing
+enum Input {
+    None,
+    Pause,
+    Quit,
+    Resize, // The terminal changed size
 }
 
-impl Default for MapItem {
-    fn default() -> Self {
-        Self::new(MapItemType::Food, Position::default())
-    }
+/// Why a single game stopped
+enum GameEnd {
+    Over(Duration), // The snake died or filled the board, time survived
+    Restart,
+    Quit,
 }
 
-#[allow(dead_code)]
+/// Terminal front-end of the game: forwards the input to the [`GameState`] and draws the result
 #[derive(Default)]
 pub struct SnakeGame {
     screen: Screen,
-    score: u16,
-    food: MapItem,
-    hazards: Vec<MapItem>,
-    hazards_count: u8,
-    snake: Snake,
+    state: Option<GameState>,
+    settings: Settings,
+    directions: DirectionQueue, // Turns typed but not played yet
     border: Border,
+    seed: Option<u64>,
+    autopilot: bool, // The bot plays instead of the keyboard
+    replay: Option<Replay>, // Recording of the current game
+    overrides: PlayOptions, // Command line options, they win over the config file
 }
 
 impl SnakeGame {
+    /// Starts a game with the settings saved in the config file
     pub fn new() -> Self {
+        Self::with_settings(Settings::load())
+    }
+
+    pub fn with_settings(settings: Settings) -> Self {
+        let mode = settings.mode;
+        let settings = mode.apply(settings);
         let mut screen: Screen = Screen::new();
         screen.init();
         screen.hide_cursor();
 
         let (width, height) = screen.get_terminal_size();
         info!("[Screen] Width: {width}, Height: {height}");
+        let border = settings.border(width, height);
         SnakeGame {
             screen,
-            score: 0,
-            food: MapItem::new(MapItemType::Food, Position::default()),
-            hazards: Vec::new(),
-            hazards_count: 20,
-            snake: Snake::default(),
-            border: Border::new(10, width - 10, 4, height - 4),
+            state: None,
+            directions: DirectionQueue::new(usize::from(settings.input_buffer)),
+            settings,
+            border,
+            seed: None,
+            autopilot: false,
+            replay: None,
+            overrides: PlayOptions::default(),
         }
     }
 
+    /// Plays the game generated from `seed` instead of a random one
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    pub fn set_autopilot(&mut self, autopilot: bool) {
+        self.autopilot = autopilot;
+    }
+
+    /// Keeps the command line options, to apply them again when the settings are reloaded
+    pub fn set_overrides(&mut self, overrides: PlayOptions) {
+        self.overrides = overrides;
+    }
+
     pub fn init(&mut self) {
-        let screen = &mut self.screen;
-        let (width, height) = screen.get_terminal_size();
-        let border: Border = self.border;
-        info!("[Border]\n{:#?}", border);
-        Drawer::draw_borders(screen, &border);
-        Drawer::draw_text(screen, format!("Score: {}", self.score).as_str(), Position::new(border.start_line - 1, border.start_col + 2));
-
-        let snake_boundaries: Border = Border::new(
-            border.start_col + 2, 
-            border.end_col - 1, 
-            border.start_line + 1, 
-            border.end_line - 1
-        );
-        let head_position = random_position(&border);
-        self.snake = Snake::new(
-            Direction::Up, 
-            SnakeNode::new(
-                head_position
-            ),
-            Some(snake_boundaries)
-        );
-        self.snake.add_tails(3);
-        self.food.set_random_position(&border, &self.snake.get_positions());
-
-        // ==== DRAWING ==== //
-        Drawer::render_map_item(&mut self.screen, &self.food);
-        Drawer::draw_snake(&mut self.screen, &self.snake);
-        Screen::flush();
-
-        // Add hazards
-        for _ in 1..=self.hazards_count {
-            let mut hazard = MapItem::new(MapItemType::Hazard, Position::default());
-            hazard.set_random_position(&border, &self.snake.get_positions());
-            Drawer::render_map_item(&mut self.screen, &hazard);
-            self.hazards.push(hazard);
+        let (width, height) = self.screen.get_terminal_size();
+        self.border = self.settings.border(width, height);
+        let state = GameState::new(self.border, &self.settings, self.seed);
+        self.state = Some(state);
+        self.screen.erase_screen();
+        self.draw();
+    }
+
+    /// Draws the whole arena from the current state
+    fn draw(&mut self) {
+        if let Some(state) = self.state.as_ref() {
+            Drawer::draw_game(&mut self.screen, state, &self.settings.palette(), &self.settings.glyphs);
         }
     }
 
-    pub fn run(&mut self) {
-        self.init();
-        let border: Border = self.border;
-        let mut game_lost = false;
-        while !game_lost {
-            // Handles input and exit if necessary
-            if let Ok(should_exit) = self.handle_input() {
-                if should_exit {
-                    break;
+    /// Plays until the player leaves from the game over screen, returning their choice
+    pub fn run(&mut self) -> GameOverChoice {
+        loop {
+            let duration = match self.play() {
+                GameEnd::Over(duration) => duration,
+                GameEnd::Restart => continue,
+                GameEnd::Quit => {
+                    self.screen.erase_screen();
+                    self.screen.flush();
+                    return GameOverChoice::MainMenu;
                 }
-            }
-            sleep(Duration::from_millis(100)); // TODO: make clock speed configurable
-
-            Drawer::delete_snake(&mut self.screen, &self.snake); // Delete previous snake
-            self.snake.update_positions();
-            Drawer::draw_snake(&mut self.screen, &self.snake); // Draws new snake
-
-            game_lost = self.snake.is_eating_tail();
-            let head_position = *self
-                .snake
-                .get_head()
-                .get_position();
-            if self.food.position == head_position {
-                info!(
-                    "Ate food at line {} and column {}",
-                    self.food.position.line, self.food.position.column
-                );
-                self.score += 1;
-                self.snake.add_tail();
-                self.food.set_random_position(&border, &self.snake.get_positions());
-                Drawer::render_map_item(
-                    &mut self.screen, 
-                    &self.food
-                );
-                Drawer::draw_text(&mut self.screen, format!("Score: {}", self.score).as_str(), Position::new(self.border.start_line - 1, self.border.start_col + 2));
+            };
+            let Some(state) = self.state.as_ref() else {
+                return GameOverChoice::MainMenu;
+            };
+            let stats = GameStats::from_state(state, duration);
+
+            if !self.autopilot && self.record_score(stats) {
+                // The name prompt used the whole screen
+                self.screen.erase_screen();
+                self.draw();
             }
 
-            for hazard in self.hazards.iter() {
-                if hazard.position == head_position {
-                    game_lost = true;
-                    break;
+            let choice = GameOverMenu::new(stats).run(&mut self.screen);
+            info!("[GameOver] {}", choice.name());
+            match choice {
+                GameOverChoice::Retry => self.seed = None,
+                GameOverChoice::RetrySameSeed => self.seed = Some(stats.seed),
+                GameOverChoice::MainMenu | GameOverChoice::Quit => {
+                    self.screen.erase_screen();
+                    self.screen.flush();
+                    return choice;
                 }
             }
+        }
+    }
+
+    /// Plays a single game until the snake dies or the player leaves it
+    fn play(&mut self) -> GameEnd {
+        // Refuses to start until the arena fits
+        let key_bindings = &self.settings.key_bindings;
+        if !self.screen.wait_for_room(self.settings.margins.min_terminal_size(), |code| key_bindings.is_quit(code)) {
+            self.screen.erase_screen();
+            self.screen.flush();
+            return GameEnd::Quit;
+        }
+        self.init();
+        self.directions = DirectionQueue::new(usize::from(self.settings.input_buffer));
+        self.replay = self.state.as_ref().map(|state| Replay::new(self.border, &self.settings, state.get_seed()));
+        // Pauses on its own when the terminal loses focus
+        self.screen.set_focus_reporting(true);
+        let end = self.play_loop();
+        self.screen.set_focus_reporting(false);
+        self.save_replay();
+        end
+    }
 
-            Screen::flush();
+    /// Saves the recording of the game that just ended, if it lasted at least a tick
+    fn save_replay(&mut self) {
+        let (Some(mut replay), Some(state)) = (self.replay.take(), self.state.as_ref()) else {
+            return;
+        };
+        if state.get_tick() == 0 {
+            return;
         }
-        Screen::erase_screen();
-        
-        if game_lost {
-            print!("You lost the game");
+        replay.finish(state);
+        if let Err(e) = replay.save_new() {
+            error!("[Replay] Could not save the replay: {e}");
         }
     }
 
-    fn handle_input(&mut self) -> Result<bool, std::io::Error> {
-        let mut should_exit: bool = false;
-        let event_available = Screen::poll_event()?;
-        if event_available {
-            let event = Screen::get_event()?;
-            debug!("Event available, {:#?}", event);
-            if let Event::Key(key) = event {
-                match key.code {
-                    KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('a') => {
-                        if self.snake.can_go_in_direction(Direction::Left) {
-                            self.snake.change_direction(Direction::Left)
+    fn play_loop(&mut self) -> GameEnd {
+        let mut clock = Clock::new(self.settings.speed_curve.tick_duration(self.settings.tick_rate, 0));
+        let started = Instant::now();
+        let mut paused = Duration::ZERO; // Not counted in the time survived
+        loop {
+            // Handles input until the next tick is due
+            let current = self.state.as_ref().map(|state| state.get_snake().get_direction()).unwrap_or(Direction::Up);
+            let input = match Self::handle_input(&mut self.directions, current, &self.settings.key_bindings, clock.remaining()) {
+                Ok(Input::Resize) => {
+                    let resize_started = Instant::now();
+                    let input = self.handle_resize();
+                    paused += resize_started.elapsed();
+                    Ok(input)
+                }
+                input => input,
+            };
+            match input {
+                Ok(Input::Quit) => return GameEnd::Quit,
+                Ok(Input::Pause) => {
+                    let pause_started = Instant::now();
+                    loop {
+                        match self.pause() {
+                            PauseChoice::Restart => return GameEnd::Restart,
+                            PauseChoice::QuitToMenu => return GameEnd::Quit,
+                            PauseChoice::Resume | PauseChoice::Settings => (), // Settings are opened from the pause screen
                         }
-                    }
-                    KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('d') => {
-                        if self.snake.can_go_in_direction(Direction::Right) {
-                            self.snake.change_direction(Direction::Right)
+                        // The pause screen doesn't follow resizes, catches up before playing again
+                        match self.handle_resize() {
+                            Input::Quit => return GameEnd::Quit,
+                            Input::Pause => continue,
+                            _ => break,
                         }
                     }
-                    KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('w') => {
-                        if self.snake.can_go_in_direction(Direction::Up) {
-                            self.snake.change_direction(Direction::Up)
-                        }
-                    }
-                    KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('s') => {
-                        if self.snake.can_go_in_direction(Direction::Down) {
-                            self.snake.change_direction(Direction::Down)
-                        }
+                    paused += pause_started.elapsed();
+                    // Keys typed before the pause are stale, and the buffer size may have changed
+                    self.directions = DirectionQueue::new(usize::from(self.settings.input_buffer));
+                    if let Some(state) = self.state.as_mut() {
+                        state.set_speed(self.settings.speed_curve, self.settings.tick_rate);
+                        clock.set_tick_duration(state.tick_duration());
                     }
-                    KeyCode::Esc | KeyCode::Char('q') => should_exit = true,
-                    _ => (),
+                    clock.reset();
+                    continue;
                 }
+                _ => (),
+            }
+            if !clock.tick() {
+                continue;
+            }
+
+            let settings = &self.settings;
+            let palette = settings.palette();
+            let glyphs = settings.glyphs;
+            let Some(state) = self.state.as_mut() else {
+                return GameEnd::Quit;
+            };
+            let input = if self.autopilot { Some(Bot::next_direction(state)) } else { self.directions.pop() };
+            Drawer::erase_moving(&mut self.screen, state); // Delete previous snake and hazards
+            // Keeping the same direction changes nothing, only turns are recorded
+            if let Some(direction) = input
+                && direction != state.get_snake().get_direction()
+                && let Some(replay) = self.replay.as_mut() {
+                replay.record(state.get_tick() + 1, direction);
+            }
+            let outcome = state.step(input);
+            Drawer::draw_moving(&mut self.screen, state, outcome, &palette, &glyphs); // Draws them again
+            // Follows the score and the speed effects, which can end on any tick
+            clock.set_tick_duration(state.tick_duration());
+
+            match outcome {
+                TickOutcome::Died(cause) => {
+                    info!("Died: {:?}", cause);
+                    self.screen.flush();
+                    return GameEnd::Over(started.elapsed().saturating_sub(paused));
+                }
+                TickOutcome::Won => {
+                    info!("Won: the board is full");
+                    self.screen.flush();
+                    return GameEnd::Over(started.elapsed().saturating_sub(paused));
+                }
+                TickOutcome::Moved | TickOutcome::AteFood => (),
+            }
+
+            self.screen.flush();
+        }
+    }
+
+    /// Follows a terminal resize: the arena is centered again, after waiting with a notice while
+    /// the terminal is too small for it. Returns `Input::Pause` if the game should wait for the
+    /// player after the notice, `Input::Quit` if they left from it.
+    fn handle_resize(&mut self) -> Input {
+        if !self.screen.update_terminal_size() {
+            return Input::None;
+        }
+        let (border_width, border_height) = self.border.get_size();
+        let (width, height) = self.screen.get_terminal_size();
+        let too_small = width < border_width || height < border_height;
+        let key_bindings = &self.settings.key_bindings;
+        if too_small && !self.screen.wait_for_room((border_width, border_height), |code| key_bindings.is_quit(code)) {
+            return Input::Quit;
+        }
+
+        let (width, height) = self.screen.get_terminal_size();
+        if let Some(border) = self.border.centered(width, height)
+            && let Some(state) = self.state.as_mut() {
+            state.move_to(border);
+            self.border = border;
+        }
+        self.screen.erase_screen();
+        self.draw();
+        if too_small { Input::Pause } else { Input::None }
+    }
+
+    /// Shows the pause screen until the player resumes, restarts or quits
+    fn pause(&mut self) -> PauseChoice {
+        info!("[Pause] Game paused");
+        loop {
+            let choice = PauseMenu::new(self.settings.key_bindings.pause.clone()).run(&mut self.screen);
+            info!("[Pause] {}", choice.name());
+            if choice != PauseChoice::Settings {
+                // Removes the box from the arena
+                self.screen.erase_screen();
+                self.draw();
+                return choice;
             }
 
-            // Empties the queue except for the last one
-            debug!("Emptied the event queue");
-            while Screen::poll_event()? {
-                let _ = Screen::get_event();
+            // The speed, theme and keys change right away, the rest on the next game
+            let saved = Settings::load();
+            let mut options = OptionsMenu::new(saved.clone());
+            options.run(&mut self.screen);
+            let mut settings = options.get_settings().clone();
+            self.overrides.apply_unchanged(&mut settings, &saved);
+            let mode = settings.mode;
+            self.settings = mode.apply(settings);
+            self.screen.erase_screen();
+            self.draw();
+        }
+    }
+
+    /// Asks for the player name if the run made it into the high scores table,
+    /// returns true if the prompt was shown
+    fn record_score(&mut self, stats: GameStats) -> bool {
+        // Same key as the tables already saved, the distance between the border lines
+        let border = self.border;
+        let table = HighScores::table_name(self.settings.mode, border.end_col - border.start_col, border.end_line - border.start_line);
+        let mut scores = HighScores::load();
+        if !scores.qualifies(&table, stats.score) {
+            return false;
+        }
+
+        let Some(name) = NamePrompt::new(stats.score).run(&mut self.screen) else {
+            return true;
+        };
+        let entry = ScoreEntry {
+            name,
+            score: stats.score,
+            length: u16::try_from(stats.max_length).unwrap_or(u16::MAX),
+            duration: stats.duration.as_secs(),
+            seed: stats.seed,
+            date: today(),
+        };
+        scores.insert(&table, entry);
+        if let Err(e) = scores.save() {
+            error!("[Scores] Could not save the high scores: {e}");
+        }
+        true
+    }
+
+    /// Waits up to `timeout` for an event and queues the requested turn, checked against the
+    /// `current` direction of the snake and the turns already queued
+    fn handle_input(directions: &mut DirectionQueue, current: Direction, bindings: &KeyBindings, timeout: Duration) -> Result<Input, std::io::Error> {
+        let event_available = Screen::poll_event(timeout)?;
+        if !event_available {
+            return Ok(Input::None);
+        }
+
+        let event = Screen::get_event()?;
+        debug!("Event available, {:#?}", event);
+        match event {
+            Event::Key(key) => {
+                if let Some(direction) = bindings.direction(key.code) {
+                    directions.push(direction, current);
+                } else if bindings.is_quit(key.code) {
+                    return Ok(Input::Quit);
+                } else if bindings.is_pause(key.code) {
+                    return Ok(Input::Pause);
+                }
             }
+            Event::FocusLost => return Ok(Input::Pause),
+            Event::Resize(..) => return Ok(Input::Resize),
+            _ => (),
         }
-        Ok(should_exit)
+        Ok(Input::None)
     }
 }
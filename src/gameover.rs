@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode};
+use log::debug;
+
+use crate::backend::Style;
+use crate::drawing::Drawer;
+use crate::scores::format_duration;
+use crate::screen::Screen;
+use crate::state::{DeathCause, GameState};
+use crate::Column;
+
+const OVERLAY_WIDTH: u16 = 34;
+const OVERLAY_HEIGHT: u16 = 18;
+
+/// Summary of a finished game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameStats {
+    pub score: u16,
+    pub max_length: usize,
+    pub duration: Duration,
+    pub food_eaten: u32,
+    pub cause: Option<DeathCause>,
+    pub won: bool, // The snake filled the board
+    pub seed: u64,
+}
+
+impl GameStats {
+    pub fn from_state(state: &GameState, duration: Duration) -> Self {
+        GameStats {
+            score: state.get_score(),
+            max_length: state.get_max_length(),
+            duration,
+            food_eaten: state.get_food_eaten(),
+            cause: state.get_death_cause(),
+            won: state.is_won(),
+            seed: state.get_seed(),
+        }
+    }
+}
+
+/// What the player picked on the game over screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverChoice {
+    Retry,
+    RetrySameSeed,
+    MainMenu,
+    Quit,
+}
+
+impl GameOverChoice {
+    pub const ALL: [GameOverChoice; 4] = [Self::Retry, Self::RetrySameSeed, Self::MainMenu, Self::Quit];
+
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Retry => Self::RetrySameSeed,
+            Self::RetrySameSeed => Self::MainMenu,
+            Self::MainMenu => Self::Quit,
+            Self::Quit => Self::Retry,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            Self::Retry => Self::Quit,
+            Self::RetrySameSeed => Self::Retry,
+            Self::MainMenu => Self::RetrySameSeed,
+            Self::Quit => Self::MainMenu,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Retry => "Retry",
+            Self::RetrySameSeed => "Retry same seed",
+            Self::MainMenu => "Main Menu",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+fn describe(cause: Option<DeathCause>) -> &'static str {
+    match cause {
+        Some(DeathCause::Tail) => "You bit your own tail",
+        Some(DeathCause::Hazard) => "You ran into a hazard",
+        Some(DeathCause::Wall) => "You crashed into a wall",
+        None => "Game over",
+    }
+}
+
+/// Box drawn over the arena once the game ends, with the stats of the game
+pub struct GameOverMenu {
+    stats: GameStats,
+    selected: GameOverChoice,
+}
+
+impl GameOverMenu {
+    pub fn new(stats: GameStats) -> Self {
+        GameOverMenu { stats, selected: GameOverChoice::Retry }
+    }
+
+    pub fn run(&mut self, screen: &mut Screen) -> GameOverChoice {
+        self.draw(screen);
+        while let Ok(event) = Screen::get_event() {
+            let Event::Key(key) = event else {
+                continue;
+            };
+            debug!("[GameOver] Found key {}", key.code);
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('w') => self.selected = self.selected.prev(),
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('s') => self.selected = self.selected.next(),
+                KeyCode::Enter | KeyCode::Char(' ') => return self.selected,
+                KeyCode::Char('r') => return GameOverChoice::Retry,
+                KeyCode::Esc | KeyCode::Char('m') => return GameOverChoice::MainMenu,
+                KeyCode::Char('q') => return GameOverChoice::Quit,
+                _ => (),
+            }
+            self.draw(screen);
+        }
+        GameOverChoice::Quit
+    }
+
+    pub fn draw(&self, screen: &mut Screen) {
+        let start = Drawer::draw_overlay(screen, OVERLAY_WIDTH, OVERLAY_HEIGHT);
+        let (start_line, start_col) = (start.line, start.column);
+
+        let center = |text: &str| -> Column {
+            let length = u16::try_from(text.chars().count()).unwrap_or(0);
+            start_col + (OVERLAY_WIDTH + 1).saturating_sub(length) / 2
+        };
+        let mut line = start_line + 2;
+        let (title, reason) = if self.stats.won {
+            (("YOU WIN", Style::colored(crate::GREEN)), "You filled the board")
+        } else {
+            (("GAME OVER", Style::colored(crate::RED)), describe(self.stats.cause))
+        };
+        for (text, style) in [title, (reason, Style::default())] {
+            screen.draw_formatted_text(line, center(text), text, style);
+            line += 1;
+        }
+
+        line += 1;
+        let stats = [
+            ("Score", self.stats.score.to_string()),
+            ("Max length", self.stats.max_length.to_string()),
+            ("Time", format_duration(self.stats.duration.as_secs())),
+            ("Food eaten", self.stats.food_eaten.to_string()),
+            ("Seed", self.stats.seed.to_string()),
+        ];
+        for (label, value) in stats {
+            let row = format!("{label:<11}{value:>20}");
+            screen.draw_formatted_text(line, center(row.as_str()), row.as_str(), Style::default());
+            line += 1;
+        }
+
+        line += 1;
+        for choice in GameOverChoice::ALL {
+            let style = if choice == self.selected { Style::underlined() } else { Style::default() };
+            screen.draw_formatted_text(line, center(choice.name()), choice.name(), style);
+            line += 1;
+        }
+        screen.flush();
+    }
+}
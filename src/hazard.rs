@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+use crate::occupancy::{Occupant, OccupancyGrid};
+use crate::settings::GameMode;
+use crate::{Direction, Position};
+
+/// Ticks between two moves of a patrolling hazard
+pub const PATROL_EVERY: u64 = 2;
+/// Ticks between two steps of a drifting hazard
+pub const DRIFT_EVERY: u64 = 6;
+/// A blinking hazard stays `BLINK_ON` ticks, then goes away for `BLINK_OFF` ticks, the last
+/// `BLINK_WARNING` of them showing where it comes back
+pub const BLINK_ON: u32 = 40;
+pub const BLINK_OFF: u32 = 30;
+pub const BLINK_WARNING: u32 = 10;
+
+/// How many hazards of each kind a mode has, taken from the `hazards_count` ones: the others don't move
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HazardRules {
+    pub patrol: u8, // Going back and forth along a line or a column
+    pub drift: u8, // Slowly coming toward the head
+    pub blink: u8, // Going away and coming back, with a warning before
+    pub multiply_every: u64, // Ticks between two new hazards, next to an existing one (0: never)
+}
+
+/// The hazard rules of every mode, `[hazard_rules.<mode>]` in the config file. Zen has no hazards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "PartialModeHazardRules")]
+pub struct ModeHazardRules {
+    pub classic: HazardRules,
+    pub hardcore: HazardRules,
+}
+
+/// [`ModeHazardRules`] as written in the config file, the rules left out keep the mode defaults
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PartialModeHazardRules {
+    classic: PartialHazardRules,
+    hardcore: PartialHazardRules,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PartialHazardRules {
+    patrol: Option<u8>,
+    drift: Option<u8>,
+    blink: Option<u8>,
+    multiply_every: Option<u64>,
+}
+
+impl PartialHazardRules {
+    fn or(self, defaults: HazardRules) -> HazardRules {
+        HazardRules {
+            patrol: self.patrol.unwrap_or(defaults.patrol),
+            drift: self.drift.unwrap_or(defaults.drift),
+            blink: self.blink.unwrap_or(defaults.blink),
+            multiply_every: self.multiply_every.unwrap_or(defaults.multiply_every),
+        }
+    }
+}
+
+impl From<PartialModeHazardRules> for ModeHazardRules {
+    fn from(partial: PartialModeHazardRules) -> Self {
+        let defaults = Self::default();
+        ModeHazardRules { classic: partial.classic.or(defaults.classic), hardcore: partial.hardcore.or(defaults.hardcore) }
+    }
+}
+
+impl Default for ModeHazardRules {
+    fn default() -> Self {
+        ModeHazardRules {
+            classic: HazardRules { patrol: 2, drift: 0, blink: 2, multiply_every: 0 },
+            hardcore: HazardRules { patrol: 4, drift: 2, blink: 4, multiply_every: 300 },
+        }
+    }
+}
+
+impl ModeHazardRules {
+    pub fn get(&self, mode: GameMode) -> HazardRules {
+        match mode {
+            GameMode::Classic => self.classic,
+            GameMode::Zen => HazardRules::default(),
+            GameMode::Hardcore => self.hardcore,
+        }
+    }
+
+    /// Returns the rules of every mode with the name of their setting
+    pub fn named(&self) -> [(&'static str, HazardRules); 2] {
+        [("hazard_rules.classic", self.classic), ("hazard_rules.hardcore", self.hardcore)]
+    }
+}
+
+/// How a hazard moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HazardBehaviour {
+    Static,
+    Patrol(Direction), // Direction it's going, turns around when something is in the way
+    Drift,
+    Blink(u32), // Ticks into its cycle: there first, then away
+}
+
+impl HazardBehaviour {
+    /// Returns true if the hazard is on the board: it kills the snake and blocks its cell
+    pub fn is_solid(&self) -> bool {
+        !matches!(self, Self::Blink(phase) if *phase >= BLINK_ON)
+    }
+
+    /// Returns true while a blinking hazard shows where it's coming back
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::Blink(phase) if *phase >= BLINK_ON + BLINK_OFF - BLINK_WARNING)
+    }
+}
+
+/// Returns true if a hazard can go on `position`: a free cell, and not right next to the head,
+/// so the snake always has a tick to see it coming
+pub fn can_enter(occupancy: &OccupancyGrid, position: &Position, head: &Position) -> bool {
+    occupancy.get(position) == Occupant::Empty && position.line.abs_diff(head.line) + position.column.abs_diff(head.column) > 1
+}
+
+/// Moves the hazard at `position` one cell in `direction` if it can, across the walls only if `wrap`.
+/// Returns false if it stayed.
+pub fn try_move(occupancy: &mut OccupancyGrid, position: &mut Position, direction: Direction, head: &Position, wrap: bool) -> bool {
+    let mut next = *position;
+    next.move_towards(direction);
+    let wrapped = next.line.abs_diff(position.line) + next.column.abs_diff(position.column) != 1;
+    if (wrapped && !wrap) || !can_enter(occupancy, &next, head) {
+        return false;
+    }
+    occupancy.set(position, Occupant::Empty);
+    occupancy.set(&next, Occupant::Hazard);
+    *position = next;
+    true
+}
+
+/// Directions bringing `position` closer to `target`, the longest way first
+pub fn directions_toward(position: &Position, target: &Position) -> Vec<Direction> {
+    let vertical = match target.line.cmp(&position.line) {
+        std::cmp::Ordering::Less => Some(Direction::Up),
+        std::cmp::Ordering::Greater => Some(Direction::Down),
+        std::cmp::Ordering::Equal => None,
+    };
+    let horizontal = match target.column.cmp(&position.column) {
+        std::cmp::Ordering::Less => Some(Direction::Left),
+        std::cmp::Ordering::Greater => Some(Direction::Right),
+        std::cmp::Ordering::Equal => None,
+    };
+    if target.line.abs_diff(position.line) >= target.column.abs_diff(position.column) {
+        [vertical, horizontal].into_iter().flatten().collect()
+    } else {
+        [horizontal, vertical].into_iter().flatten().collect()
+    }
+}
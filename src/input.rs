@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+
+use log::debug;
+
+use crate::Direction;
+
+/// Turns typed faster than the snake moves, applied one per tick. Each turn is checked against
+/// the direction the snake will have when it's applied, so a quick "up, left" while going right
+/// is two turns and not a U-turn.
+#[derive(Debug, Clone, Default)]
+pub struct DirectionQueue {
+    pending: VecDeque<Direction>,
+    capacity: usize,
+}
+
+impl DirectionQueue {
+    pub fn new(capacity: usize) -> Self {
+        DirectionQueue { pending: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    /// Queues `direction` after the pending turns, returns false if it was dropped: the queue is
+    /// full, or it's no turn at all from the direction in effect by then (same or opposite one)
+    pub fn push(&mut self, direction: Direction, current: Direction) -> bool {
+        let in_effect = self.pending.back().copied().unwrap_or(current);
+        if direction == in_effect || direction == in_effect.opposite() || self.pending.len() >= self.capacity {
+            debug!("[Input] Dropped {direction:?} (in effect: {in_effect:?}, pending: {})", self.pending.len());
+            return false;
+        }
+        self.pending.push_back(direction);
+        true
+    }
+
+    /// Returns the turn for the next tick
+    pub fn pop(&mut self) -> Option<Direction> {
+        self.pending.pop_front()
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_turns_are_applied_one_per_tick() {
+        let mut queue = DirectionQueue::new(3);
+        assert!(queue.push(Direction::Up, Direction::Right));
+        assert!(queue.push(Direction::Left, Direction::Right)); // Not a U-turn once going up
+
+        assert_eq!(queue.pop(), Some(Direction::Up));
+        assert_eq!(queue.pop(), Some(Direction::Left));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn no_turns_and_u_turns_are_dropped() {
+        let mut queue = DirectionQueue::new(3);
+        assert!(!queue.push(Direction::Right, Direction::Right));
+        assert!(!queue.push(Direction::Left, Direction::Right));
+        assert!(queue.push(Direction::Up, Direction::Right));
+        assert!(!queue.push(Direction::Down, Direction::Right));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn turns_past_the_capacity_are_dropped() {
+        let mut queue = DirectionQueue::new(2);
+        assert!(queue.push(Direction::Up, Direction::Right));
+        assert!(queue.push(Direction::Left, Direction::Right));
+        assert!(!queue.push(Direction::Down, Direction::Right));
+
+        queue.clear();
+        assert!(queue.is_empty());
+        assert!(queue.push(Direction::Down, Direction::Right));
+    }
+}
@@ -0,0 +1,124 @@
+use crossterm::event::{Event, KeyCode};
+use log::debug;
+
+use crate::backend::Style;
+use crate::scores::{format_duration, HighScores};
+use crate::screen::Screen;
+use crate::{Column, Line};
+
+const MAX_NAME_LENGTH: usize = 12;
+
+fn draw_centered(screen: &mut Screen, line: Line, text: &str, style: Style) {
+    let (width, _) = screen.get_terminal_size();
+    let length: Column = u16::try_from(text.chars().count()).unwrap_or(0);
+    screen.draw_formatted_text(line, width.saturating_sub(length) / 2, text, style);
+}
+
+/// Asks for the name to write in the high scores table
+pub struct NamePrompt {
+    name: String,
+    score: u16,
+}
+
+impl NamePrompt {
+    pub fn new(score: u16) -> Self {
+        let mut name = std::env::var("USER").unwrap_or_default();
+        name.truncate(MAX_NAME_LENGTH);
+        NamePrompt { name, score }
+    }
+
+    /// Returns the typed name, or None if the player pressed Esc
+    pub fn run(&mut self, screen: &mut Screen) -> Option<String> {
+        self.draw(screen);
+        while let Ok(event) = Screen::get_event() {
+            let Event::Key(key) = event else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Enter if !self.name.trim().is_empty() => return Some(self.name.trim().to_string()),
+                KeyCode::Esc => return None,
+                KeyCode::Backspace => {
+                    self.name.pop();
+                }
+                KeyCode::Char(c) if !c.is_control() && self.name.chars().count() < MAX_NAME_LENGTH => self.name.push(c),
+                _ => (),
+            }
+            self.draw(screen);
+        }
+        None
+    }
+
+    fn draw(&self, screen: &mut Screen) {
+        let (_, height) = screen.get_terminal_size();
+        let line = (height / 2).saturating_sub(2).max(1);
+        screen.erase_screen();
+        draw_centered(screen, line, format!("New high score: {}!", self.score).as_str(), Style::underlined());
+        draw_centered(screen, line + 2, "Enter your name:", Style::default());
+        let field = format!("{:_<width$}", self.name, width = MAX_NAME_LENGTH);
+        draw_centered(screen, line + 4, field.as_str(), Style::colored(crate::GREEN));
+        draw_centered(screen, line + 6, "Enter confirm · Esc skip", Style::colored(8));
+        screen.flush();
+    }
+}
+
+/// Browses the high scores tables, one mode and arena size at a time
+pub struct ScoresMenu {
+    scores: HighScores,
+    selected_table: usize,
+}
+
+impl ScoresMenu {
+    pub fn new(scores: HighScores) -> Self {
+        ScoresMenu { scores, selected_table: 0 }
+    }
+
+    pub fn run(&mut self, screen: &mut Screen) {
+        self.draw(screen);
+        let tables = self.scores.get_table_names().len().max(1);
+        while let Ok(event) = Screen::get_event() {
+            let Event::Key(key) = event else {
+                continue;
+            };
+            debug!("[Scores] Found key {}", key.code);
+            match key.code {
+                KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('a') => {
+                    self.selected_table = (self.selected_table + tables - 1) % tables;
+                }
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('d') => {
+                    self.selected_table = (self.selected_table + 1) % tables;
+                }
+                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => break,
+                _ => (),
+            }
+            self.draw(screen);
+        }
+    }
+
+    pub fn draw(&self, screen: &mut Screen) {
+        screen.erase_screen();
+        draw_centered(screen, 2, "High Scores", Style::underlined());
+
+        let names = self.scores.get_table_names();
+        let Some(table) = names.get(self.selected_table) else {
+            draw_centered(screen, 5, "No scores yet, go play!", Style::default());
+            draw_centered(screen, 7, "Esc back", Style::colored(8));
+            screen.flush();
+            return;
+        };
+        draw_centered(screen, 4, format!("< {table} >").as_str(), Style::default());
+
+        let header = format!("{:>2}  {:<12} {:>5} {:>6} {:>6} {:>20}  {:<10}", "#", "Name", "Score", "Length", "Time", "Seed", "Date");
+        draw_centered(screen, 6, header.as_str(), Style::colored(8));
+        let mut line = 7;
+        for (rank, entry) in self.scores.get_table(table).iter().enumerate() {
+            let row = format!(
+                "{:>2}  {:<12} {:>5} {:>6} {:>6} {:>20}  {:<10}",
+                rank + 1, entry.name, entry.score, entry.length, format_duration(entry.duration), entry.seed, entry.date
+            );
+            draw_centered(screen, line, row.as_str(), Style::default());
+            line += 1;
+        }
+        draw_centered(screen, line + 1, "←/→ mode and arena · Esc back", Style::colored(8));
+        screen.flush();
+    }
+}
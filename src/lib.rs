@@ -1,24 +1,49 @@
-use log::info;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
+pub mod asciicast;
+pub mod backend;
+pub mod bot;
+pub mod cli;
+pub mod clock;
+pub mod config;
+pub mod food;
 pub mod game;
+pub mod gameover;
+pub mod hazard;
+pub mod input;
+pub mod leaderboard;
 pub mod menu;
+pub mod occupancy;
+pub mod options;
+pub mod pause;
+pub mod powerup;
+pub mod replay;
+pub mod scores;
+pub mod settings;
+pub mod snake;
+pub mod state;
+pub mod svg;
+pub mod viewer;
+pub mod drawing;
+pub mod frame;
+pub mod screen;
 mod cursor;
-mod drawing;
-mod screen;
-mod snake;
 
 const ESC: &str = "\x1b";
 const WHITE: u16 = 15;
 const GREEN: u16 = 2;
-const BLUE: u16 = 4;
-const DARK_BLUE: u16 = 18;
 const RED: u16 = 1;
+/// Smallest playfield a game starts on, in both directions
+const MIN_PLAYFIELD_SIZE: u16 = 3;
+/// Lines of the score (above the border) and the seed (under it)
+const HUD_LINES: u16 = 2;
 type Line = u16;
 type Column = u16;
 type Height = u16;
 type Width = u16;
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Border {
     pub start_col: Column,
     pub end_col: Column,
@@ -31,13 +56,132 @@ impl Border {
     pub fn new(start_col: Column, end_col: Column, start_line: Line, end_line: Line) -> Self {
         Border { start_col, start_line, end_col, end_line }
     }
+
+    /// Columns and lines taken, border lines included
+    pub fn get_size(&self) -> (Width, Height) {
+        (self.end_col.saturating_sub(self.start_col) + 1, self.end_line.saturating_sub(self.start_line) + 1)
+    }
+
+    /// Returns true if a terminal of `width` x `height` shows the whole border, around a playfield
+    /// big enough to play
+    pub fn fits(&self, width: Width, height: Height) -> bool {
+        let playfield = Playfield::new(self);
+        self.end_col <= width && self.end_line <= height
+            && self.start_col < self.end_col && self.start_line < self.end_line
+            && playfield.get_width() >= MIN_PLAYFIELD_SIZE && playfield.get_height() >= MIN_PLAYFIELD_SIZE
+    }
+
+    /// The same border centered in a terminal of `width` x `height`, with the score and seed
+    /// lines if there's room for them. None if the terminal is too small for it.
+    pub fn centered(&self, width: Width, height: Height) -> Option<Border> {
+        let (border_width, border_height) = self.get_size();
+        if border_width > width || border_height > height {
+            return None;
+        }
+        let start_col = (width - border_width) / 2 + 1;
+        let start_line = match height.checked_sub(border_height + HUD_LINES) {
+            Some(room) => room / 2 + 2, // Under the score line
+            None => (height - border_height) / 2 + 1,
+        };
+        Some(Border::new(start_col, start_col + border_width - 1, start_line, start_line + border_height - 1))
+    }
+}
+
+/// Zero-based coordinates of a cell inside a [`Playfield`], from its top left cell
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GridCell {
+    pub row: u16,
+    pub col: u16,
+}
+
+/// Cells the snake moves on: the inside of a [`Border`], bounds included, in screen coordinates.
+/// Spawning, moving, wrapping and collisions all use it, the border itself is only drawn.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Playfield {
+    pub first_line: Line,
+    pub last_line: Line,
+    pub first_col: Column,
+    pub last_col: Column,
+}
+
+impl Playfield {
+    /// Returns the cells inside the border lines
+    pub fn new(border: &Border) -> Self {
+        Playfield {
+            first_line: border.start_line + 1,
+            last_line: border.end_line.saturating_sub(1).max(border.start_line + 1),
+            first_col: border.start_col + 1,
+            last_col: border.end_col.saturating_sub(1).max(border.start_col + 1),
+        }
+    }
+
+    /// Number of columns
+    pub fn get_width(&self) -> Width {
+        self.last_col - self.first_col + 1
+    }
+
+    /// Number of lines
+    pub fn get_height(&self) -> Height {
+        self.last_line - self.first_line + 1
+    }
+
+    pub fn cell_count(&self) -> usize {
+        usize::from(self.get_width()) * usize::from(self.get_height())
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        (self.first_line..=self.last_line).contains(&position.line) && (self.first_col..=self.last_col).contains(&position.column)
+    }
+
+    /// Screen position of a cell, wrapping inside the playfield
+    pub fn to_screen(&self, cell: GridCell) -> Position {
+        let mut position = Position::new(
+            self.first_line + cell.row % self.get_height(),
+            self.first_col + cell.col % self.get_width(),
+        );
+        position.set_boundaries(*self);
+        position
+    }
+
+    /// Cell at a screen position, None if it's outside of the playfield
+    pub fn to_cell(&self, position: &Position) -> Option<GridCell> {
+        self.contains(position).then(|| GridCell {
+            row: position.line - self.first_line,
+            col: position.column - self.first_col,
+        })
+    }
+
+    /// Every position of the playfield, line by line
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.get_height()).flat_map(move |row| (0..self.get_width()).map(move |col| self.to_screen(GridCell { row, col })))
+    }
+
+    /// Any cell of the playfield, every one with the same chance
+    pub fn random_position<R: Rng>(&self, rng: &mut R) -> Position {
+        self.to_screen(GridCell { row: rng.random_range(0..self.get_height()), col: rng.random_range(0..self.get_width()) })
+    }
+
+    /// Returns the position at the same place inside `other`, a playfield of the same size.
+    /// Positions outside of this playfield don't move.
+    pub fn translate(&self, position: &Position, other: &Playfield) -> Position {
+        self.to_cell(position).map(|cell| other.to_screen(cell)).unwrap_or(*position)
+    }
+
+    /// Moves `value` by `offset` forward or backward inside `first..=last`, coming back from the other side
+    fn wrap(value: u16, offset: u16, first: u16, last: u16, forward: bool) -> u16 {
+        let size = u32::from(last - first + 1);
+        let index = u32::from(value.clamp(first, last) - first);
+        let offset = u32::from(offset) % size;
+        let index = if forward { (index + offset) % size } else { (index + size - offset) % size };
+        first + u16::try_from(index).unwrap_or(0)
+    }
 }
 
 #[derive(Default, Debug, Clone, Copy)]
 pub struct Position {
     pub line: u16,
     pub column: u16,
-    pub boundaries: Option<Border>,
+    pub boundaries: Option<Playfield>,
 }
 
 #[allow(dead_code)]
@@ -54,51 +198,62 @@ impl Position {
         self.column = column;
     }
 
-    pub fn set_boundaries(&mut self, boundaries: Border) {
+    pub fn set_boundaries(&mut self, boundaries: Playfield) {
         self.boundaries = Some(boundaries);
     }
 
     pub fn increment_col(&mut self, offset: Column) {
-        let mut new_column = self.column + offset;
-        if let Some(boundaries) = self.boundaries {
-            if new_column >= boundaries.end_col {
-                info!("[Increasing column] New Column: {new_column}, End Column: {}", boundaries.end_col);
-                new_column = boundaries.start_col;
-            }
-        }
+        let new_column = match self.boundaries {
+            Some(field) => Playfield::wrap(self.column, offset, field.first_col, field.last_col, true),
+            None => self.column + offset,
+        };
         self.set_column(new_column);
     }
 
     pub fn decrement_col(&mut self, offset: Column) {
-        let mut new_column = self.column.saturating_sub(offset);
-        if let Some(boundaries) = self.boundaries {
-            if new_column < boundaries.start_col {
-                new_column = boundaries.end_col;
-            }
-        }
+        let new_column = match self.boundaries {
+            Some(field) => Playfield::wrap(self.column, offset, field.first_col, field.last_col, false),
+            None => self.column.saturating_sub(offset),
+        };
         self.set_column(new_column);
     }
 
-    pub fn increment_line(&mut self, offset: Column) {
-        let mut new_line = self.line + offset;
-        if let Some(boundaries) = self.boundaries {
-            if new_line > boundaries.end_line {
-                new_line = boundaries.start_line;
-            }
-        }
+    pub fn increment_line(&mut self, offset: Line) {
+        let new_line = match self.boundaries {
+            Some(field) => Playfield::wrap(self.line, offset, field.first_line, field.last_line, true),
+            None => self.line + offset,
+        };
         self.set_line(new_line);
     }
 
-    pub fn decrement_line(&mut self, offset: Column) {
-        let mut new_line = self.line.saturating_sub(offset);
-        if let Some(boundaries) = self.boundaries {
-            if new_line < boundaries.start_line {
-                new_line = boundaries.end_line;
-            }
-        }
+    pub fn decrement_line(&mut self, offset: Line) {
+        let new_line = match self.boundaries {
+            Some(field) => Playfield::wrap(self.line, offset, field.first_line, field.last_line, false),
+            None => self.line.saturating_sub(offset),
+        };
         self.set_line(new_line);
     }
 
+    /// Moves by one cell in `direction`, wrapping around the boundaries
+    pub fn move_towards(&mut self, direction: Direction) {
+        match direction {
+            Direction::Up => self.decrement_line(1),
+            Direction::Down => self.increment_line(1),
+            Direction::Right => self.increment_col(1),
+            Direction::Left => self.decrement_col(1),
+        }
+    }
+
+    /// Returns the direction of the step to `other` when it's a neighbouring cell, across the
+    /// boundaries too
+    pub fn direction_to(&self, other: &Position) -> Option<Direction> {
+        Direction::ALL.into_iter().find(|&direction| {
+            let mut next = *self;
+            next.move_towards(direction);
+            next == *other
+        })
+    }
+
     pub fn equals(pos1: &Self, pos2: &Self) -> bool {
         pos1.line == pos2.line && pos1.column == pos2.column
     }
@@ -117,3 +272,74 @@ pub enum Direction {
     Left,
     Right,
 }
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [Self::Up, Self::Down, Self::Left, Self::Right];
+
+    /// Returns the direction after a right turn
+    pub fn clockwise(&self) -> Self {
+        match self {
+            Self::Up => Self::Right,
+            Self::Right => Self::Down,
+            Self::Down => Self::Left,
+            Self::Left => Self::Up,
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_wrap_at_the_seams_of_the_playfield() {
+        let field = Playfield::new(&Border::new(1, 11, 2, 7)); // Lines 3..=6, columns 2..=10
+        let mut position = field.to_screen(GridCell { row: 0, col: 0 });
+        assert_eq!((position.line, position.column), (3, 2));
+
+        position.move_towards(Direction::Up);
+        assert_eq!((position.line, position.column), (6, 2));
+        position.move_towards(Direction::Left);
+        assert_eq!((position.line, position.column), (6, 10));
+        position.move_towards(Direction::Down);
+        assert_eq!((position.line, position.column), (3, 10));
+        position.move_towards(Direction::Right);
+        assert_eq!((position.line, position.column), (3, 2));
+    }
+
+    #[test]
+    fn wrap_handles_offsets_longer_than_the_playfield() {
+        assert_eq!(Playfield::wrap(5, 7, 3, 6, true), 4);
+        assert_eq!(Playfield::wrap(5, 7, 3, 6, false), 6);
+        assert_eq!(Playfield::wrap(1, 1, 3, 6, true), 4); // Outside: starts from the nearest edge
+    }
+
+    #[test]
+    fn cells_and_screen_positions_match() {
+        let field = Playfield::new(&Border::new(1, 11, 2, 7));
+        assert_eq!(field.cell_count(), 36);
+        assert_eq!(field.positions().count(), field.cell_count());
+        for position in field.positions() {
+            let cell = field.to_cell(&position).unwrap();
+            assert_eq!(field.to_screen(cell), position);
+        }
+        assert_eq!(field.to_cell(&Position::new(2, 2)), None);
+    }
+
+    #[test]
+    fn centered_leaves_room_for_the_score_and_seed_lines() {
+        let border = Border::new(1, 10, 1, 6);
+        assert_eq!(border.centered(20, 8), Some(Border::new(6, 15, 2, 7)));
+        assert_eq!(border.centered(20, 6), Some(Border::new(6, 15, 1, 6)));
+        assert_eq!(border.centered(20, 5), None);
+    }
+}
@@ -1,16 +1,204 @@
 use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
 use simplelog::{Config, LevelFilter, WriteLogger};
-use snake::{game::SnakeGame, menu::Menu};
+use snake::bot::Bot;
+use snake::cli::{Cli, Command, ExportSource, PlayOptions, USAGE};
+use snake::gameover::GameOverChoice;
+use snake::replay::Replay;
+use snake::scores::{format_duration, HighScores};
+use snake::settings::Settings;
+use snake::state::GameState;
+use snake::viewer::ReplayViewer;
+use snake::{asciicast, config, game::SnakeGame, menu::Menu, svg};
+
+/// Ticks after which a headless bot game is stopped, in case the bot loops forever
+const MAX_BOT_TICKS: u64 = 10_000;
+/// Terminal size the arena of an input script is computed for
+const SCRIPT_SCREEN_SIZE: (u16, u16) = (80, 24);
 
 fn main() {
-    let _ = WriteLogger::init(
-        LevelFilter::Debug,
-        Config::default(),
-        File::create("snake.log").unwrap(),
-    );
-
-    // let mut game: SnakeGame = SnakeGame::new();
-    // game.run();
-    let mut main_menu: Menu = Menu::new();
-    main_menu.run();
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("snake: {e}\nRun 'snake help' to see the usage");
+            exit(2);
+        }
+    };
+
+    // Logs only if asked to
+    if cli.log_file.is_some() || cli.log_level.is_some() {
+        let path = cli.log_file.clone().unwrap_or_else(|| PathBuf::from("snake.log"));
+        let file = match File::create(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("snake: {}: {e}", path.display());
+                exit(1);
+            }
+        };
+        let _ = WriteLogger::init(cli.log_level.unwrap_or(LevelFilter::Debug), Config::default(), file);
+    }
+
+    if let Some(path) = cli.config {
+        config::set_config_path(path);
+    }
+
+    // Stops before touching the terminal, so the error stays readable
+    let settings = match config::load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("snake: {e}");
+            exit(1);
+        }
+    };
+
+    match cli.command {
+        Command::Menu => {
+            let mut main_menu: Menu = Menu::new();
+            main_menu.run();
+        }
+        Command::Play(options) => play(settings, &options, false),
+        Command::Bot { options, games: None } => play(settings, &options, true),
+        Command::Bot { options, games: Some(games) } => simulate(settings, &options, games),
+        Command::Scores => print_scores(),
+        Command::Replay(path) => replay(&path),
+        Command::Export { source, output } => export(settings, &source, output),
+        Command::Help => print!("{USAGE}"),
+    }
+}
+
+/// Applies the command line options over the config file ones
+fn settings_with(mut settings: Settings, options: &PlayOptions) -> Settings {
+    options.apply(&mut settings);
+    if let Err((setting, reason)) = config::validate(&settings) {
+        eprintln!("snake: invalid `{setting}`: {reason}");
+        exit(2);
+    }
+    settings
+}
+
+fn play(settings: Settings, options: &PlayOptions, autopilot: bool) {
+    let mut game = SnakeGame::with_settings(settings_with(settings, options));
+    if let Some(seed) = options.seed {
+        game.set_seed(seed);
+    }
+    game.set_autopilot(autopilot);
+    game.set_overrides(options.clone());
+    if game.run() == GameOverChoice::MainMenu {
+        drop(game);
+        Menu::new().run();
+    }
+}
+
+/// Plays `games` bot games without drawing them, then prints how they went
+fn simulate(settings: Settings, options: &PlayOptions, games: u32) {
+    let settings = settings_with(settings, options);
+    let mode = settings.mode;
+    let settings = mode.apply(settings);
+    let (width, height) = terminal_size::terminal_size()
+        .map(|(width, height)| (width.0, height.0))
+        .unwrap_or((80, 24));
+    let border = settings.border(width, height);
+
+    let mut total_score: u64 = 0;
+    for game in 0..games {
+        let seed = options.seed.map(|seed| seed.wrapping_add(u64::from(game)));
+        let mut state = GameState::new(border, &settings, seed);
+        let ending = match Bot::play(&mut state, MAX_BOT_TICKS) {
+            Some(cause) => format!("died ({cause:?})"),
+            None if state.is_won() => String::from("won"),
+            None => String::from("still alive"),
+        };
+        println!(
+            "Game {:>3}  seed {:>20}  score {:>4}  ticks {:>6}  {ending}",
+            game + 1, state.get_seed(), state.get_score(), state.get_tick()
+        );
+        total_score += u64::from(state.get_score());
+    }
+    if games > 0 {
+        println!("Average score: {:.1}", total_score as f64 / f64::from(games));
+    }
+}
+
+fn load_replay(path: &Path) -> Replay {
+    match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("snake: {e}");
+            exit(1);
+        }
+    }
+}
+
+fn replay(path: &Path) {
+    let replay = load_replay(path);
+    // Room for the arena and the playback controls under it
+    let (width, height) = terminal_size::terminal_size()
+        .map(|(width, height)| (width.0, height.0))
+        .unwrap_or((0, 0));
+    let (needed_width, needed_height) = (replay.border.end_col + 1, replay.border.end_line + 3);
+    if width < needed_width || height < needed_height {
+        eprintln!("snake: the replay needs a terminal of at least {needed_width}x{needed_height}");
+        exit(1);
+    }
+    ReplayViewer::new(replay).run();
+}
+
+/// Writes a game as an asciinema recording or an animated SVG, without touching the terminal
+fn export(settings: Settings, source: &ExportSource, output: Option<PathBuf>) {
+    let (replay, path) = match source {
+        ExportSource::Replay(path) => (load_replay(path), path),
+        ExportSource::Script { options, inputs } => {
+            let settings = settings_with(settings, options);
+            let mode = settings.mode;
+            let settings = mode.apply(settings);
+            let (width, height) = SCRIPT_SCREEN_SIZE;
+            let script = std::fs::read_to_string(inputs).unwrap_or_else(|e| {
+                eprintln!("snake: {}: {e}", inputs.display());
+                exit(1);
+            });
+            let seed = options.seed.unwrap_or_default();
+            let replay = Replay::from_script(settings.border(width, height), &settings, seed, &script).unwrap_or_else(|e| {
+                eprintln!("snake: {}: {e}", inputs.display());
+                exit(1);
+            });
+            (replay, inputs)
+        }
+    };
+
+    let output = output.unwrap_or_else(|| path.with_extension("cast"));
+    let as_svg = output.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+    let result = File::create(&output).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        if as_svg {
+            svg::export(&replay, &mut out)?;
+        } else {
+            asciicast::export(&replay, &mut out)?;
+        }
+        out.flush()
+    });
+    if let Err(e) = result {
+        eprintln!("snake: {}: {e}", output.display());
+        exit(1);
+    }
+    println!("Saved {}", output.display());
+}
+
+fn print_scores() {
+    let scores = HighScores::load();
+    let tables = scores.get_table_names();
+    if tables.is_empty() {
+        println!("No high scores yet");
+    }
+    for table in tables {
+        println!("{table}");
+        for (rank, entry) in scores.get_table(table).iter().enumerate() {
+            println!(
+                "  {:>2}. {:<12} {:>5} pts  length {:>4}  {:>6}  seed {:>20}  {}",
+                rank + 1, entry.name, entry.score, entry.length, format_duration(entry.duration), entry.seed, entry.date
+            );
+        }
+        println!();
+    }
 }
@@ -1,9 +1,9 @@
-use std::{collections::HashMap, option, thread::sleep, time::Duration};
+use std::collections::HashMap;
 
 use crossterm::event::{Event, KeyCode};
 use log::debug;
 
-use crate::{game::SnakeGame, screen::{self, Screen}, Position, ESC};
+use crate::{backend::Style, game::SnakeGame, gameover::GameOverChoice, leaderboard::ScoresMenu, options::OptionsMenu, scores::HighScores, screen::Screen, settings::Settings, Position};
 
 struct MenuOption {
     text: String,
@@ -15,6 +15,7 @@ impl MenuOption {
         MenuOption { text, start_position: Position::default() }
     }
 
+    #[allow(dead_code)]
     pub fn new_with_position(text: String, start: Position) -> Self {
         MenuOption { text, start_position: start }
     }
@@ -28,6 +29,7 @@ impl MenuOption {
 pub enum MenuOptionType {
     Play,
     Options,
+    HighScores,
     Quit,
 }
 
@@ -35,7 +37,8 @@ impl MenuOptionType {
     fn next(&self) -> Self {
         match self {
             Self::Play => Self::Options,
-            Self::Options => Self::Quit,
+            Self::Options => Self::HighScores,
+            Self::HighScores => Self::Quit,
             Self::Quit => Self::Play,
         }
     }
@@ -44,7 +47,8 @@ impl MenuOptionType {
         match self {
             Self::Play => Self::Quit,
             Self::Options => Self::Play,
-            Self::Quit => Self::Options,
+            Self::HighScores => Self::Options,
+            Self::Quit => Self::HighScores,
         }
     }
 }
@@ -65,10 +69,15 @@ impl Menu {
     pub fn new() -> Self {
         let mut screen = Screen::new();
         screen.init();
+        Self::with_screen(screen)
+    }
 
+    /// Builds the menu on a screen that's already initialized
+    pub fn with_screen(screen: Screen) -> Self {
         let mut options: HashMap<MenuOptionType, MenuOption> = HashMap::new();
         options.insert(MenuOptionType::Play, MenuOption::new(String::from("New Game")));
         options.insert(MenuOptionType::Options, MenuOption::new(String::from("Options")));
+        options.insert(MenuOptionType::HighScores, MenuOption::new(String::from("High Scores")));
         options.insert(MenuOptionType::Quit, MenuOption::new(String::from("Quit")));
 
         Menu { screen, options, selected_option: MenuOptionType::Play }
@@ -77,12 +86,21 @@ impl Menu {
     pub fn run(&mut self) {
         self.draw();
 
-        while let Ok(Event::Key(key)) = Screen::get_event() {
+        while let Ok(event) = Screen::get_event() {
+            let key = match event {
+                Event::Key(key) => key,
+                Event::Resize(..) => {
+                    self.screen.update_terminal_size();
+                    self.draw();
+                    continue;
+                }
+                _ => continue, // Ignores the focus events left over by the game
+            };
             debug!("Found key {}", key.code);
             // Resets previous selected option formatting
             if let Some(option) = self.options.get(&self.selected_option) {
                 let pos = option.start_position;
-                self.screen.draw_formatted_text(pos.line, pos.column, option.text.as_str(), 0);
+                self.screen.draw_formatted_text(pos.line, pos.column, option.text.as_str(), Style::default());
             }
 
             match key.code {
@@ -90,13 +108,28 @@ impl Menu {
                     match self.selected_option {
                         MenuOptionType::Play => {
                             let mut game = SnakeGame::new();
-                            game.run();
-
-                            // After the game ends, the menu re-renders
+                            if game.run() == GameOverChoice::Quit {
+                                break;
+                            }
+
+                            // After the game ends, the menu re-renders, at the size the terminal has now
+                            if !self.screen.update_terminal_size() {
+                                self.screen.redraw();
+                            }
+                            self.draw();
+                        },
+                        MenuOptionType::Options => {
+                            let mut options = OptionsMenu::new(Settings::load());
+                            options.run(&mut self.screen);
+                            self.screen.update_terminal_size();
+                            self.draw();
+                        },
+                        MenuOptionType::HighScores => {
+                            let mut scores = ScoresMenu::new(HighScores::load());
+                            scores.run(&mut self.screen);
+                            self.screen.update_terminal_size();
                             self.draw();
-                            // TODO Game over menu
                         },
-                        MenuOptionType::Options => {},
                         MenuOptionType::Quit => break,
                     }
                 },
@@ -111,20 +144,19 @@ impl Menu {
             } 
 
             if let Some(option) = self.options.get(&self.selected_option) {
-                self.screen.cursor.jump_to_position(option.start_position);
-                print!("{ESC}[4m{}", option.text);
-                print!("{ESC}[24m"); // Reset underline
+                let pos = option.start_position;
+                self.screen.draw_formatted_text(pos.line, pos.column, option.text.as_str(), Style::underlined());
             }
-            Screen::flush();
+            self.screen.flush();
         }
 
-        Screen::erase_screen();
-        Screen::flush();
+        self.screen.erase_screen();
+        self.screen.flush();
     }
 
     pub fn draw(&mut self) {
         let (width, _) = self.screen.get_terminal_size();
-        let cursor = &mut self.screen.cursor;
+        let screen = &mut self.screen;
         let text = indoc::indoc! {
             r"                                                        
               .--.--.                                ,-.            
@@ -142,7 +174,7 @@ impl Menu {
                                    `--`---'                `----'   "
         };
 
-        Screen::erase_screen();
+        screen.erase_screen();
 
         // ASCII Art Drawing
         let text_width: u16 = u16::try_from(text.lines()
@@ -152,35 +184,53 @@ impl Menu {
             .unwrap_or(0);
         let start_col = (width.saturating_sub(text_width)) / 2;
         let start_row = 1;
-        cursor.jump(start_row, start_col);
+        screen.cursor.jump(start_row, start_col);
         for line in text.lines() {
-            print!("{}", line);
-            cursor.jump_to_col(start_col);
-            cursor.down(1);
+            screen.print(line);
+            screen.cursor.jump_to_col(start_col);
+            screen.cursor.down(1);
         }
 
         // Options rendering
         let options = &mut self.options;
-        let available_options = [MenuOptionType::Play, MenuOptionType::Options, MenuOptionType::Quit];
+        let available_options = [MenuOptionType::Play, MenuOptionType::Options, MenuOptionType::HighScores, MenuOptionType::Quit];
         for key in available_options.iter() {
             if let Some(option) = options.get_mut(key) {
-                cursor.down(2);
+                screen.cursor.down(2);
                 let text_width: u16 = u16::try_from(option.text.len()).unwrap_or(0);
                 let start_col = (width.saturating_sub(text_width)) / 2;
-                cursor.jump_to_col(start_col);
-
-                if *key == self.selected_option {
-                    print!("{ESC}[4m{}", option.text);
-                    print!("{ESC}[24m"); // Reset underline
-                } else {
-                    print!("{}", option.text);
-                }
+                screen.cursor.jump_to_col(start_col);
 
-                let position = *cursor.get_position();
+                let position = *screen.cursor.get_position();
                 option.set_position(position);
+                let style = if *key == self.selected_option { Style::underlined() } else { Style::default() };
+                screen.draw_formatted_text(position.line, position.column, option.text.as_str(), style);
             }
         }
 
-        Screen::flush();
+        screen.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+
+    #[test]
+    fn draw_centers_the_options_and_underlines_the_selected_one() {
+        let backend = MemoryBackend::new(80, 30);
+        let mut menu = Menu::with_screen(Screen::with_backend(Box::new(backend.clone())));
+        menu.draw();
+
+        let lines: Vec<String> = (1..=30).map(|line| backend.get_line(line)).collect();
+        let play = lines.iter().position(|line| line.trim() == "New Game").expect("New Game should be drawn");
+        let line = u16::try_from(play).unwrap() + 1;
+        assert_eq!(lines[play], format!("{}New Game", " ".repeat(35)));
+        assert_eq!(lines[play + 2].trim(), "Options");
+        assert_eq!(lines[play + 4].trim(), "High Scores");
+        assert_eq!(lines[play + 6].trim(), "Quit");
+        assert!(backend.get_cell(line, 36).unwrap().style.underline);
+        assert!(!backend.get_cell(line + 2, 36).unwrap().style.underline);
     }
 }
@@ -0,0 +1,223 @@
+use rand::Rng;
+
+use crate::{GridCell, Playfield, Position};
+
+/// What's on a cell of the playfield
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Occupant {
+    #[default]
+    Empty,
+    Snake,
+    Food,
+    Hazard,
+    PowerUp,
+    Portal,
+}
+
+/// Cells reachable from a start cell, found by [`OccupancyGrid::flood_fill`]
+#[derive(Debug, Clone, Default)]
+pub struct Reach {
+    playfield: Playfield,
+    reached: Vec<bool>, // Indexed like the cells of the grid
+    count: usize,
+}
+
+impl Reach {
+    /// Number of cells reached
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        self.playfield.to_cell(position).is_some_and(|cell| {
+            self.reached[usize::from(cell.row) * usize::from(self.playfield.get_width()) + usize::from(cell.col)]
+        })
+    }
+}
+
+/// What's on every cell of the playfield, updated as things move. The free cells are also kept
+/// in a list, so a random free cell is picked in constant time, every one with the same chance.
+#[derive(Debug, Clone)]
+pub struct OccupancyGrid {
+    playfield: Playfield,
+    cells: Vec<Occupant>,
+    free: Vec<usize>, // Indexes of the empty cells, in no particular order
+    free_slots: Vec<Option<usize>>, // Where each cell is in `free`, None if it's occupied
+}
+
+impl OccupancyGrid {
+    pub fn new(playfield: Playfield) -> Self {
+        let count = playfield.cell_count();
+        OccupancyGrid {
+            playfield,
+            cells: vec![Occupant::Empty; count],
+            free: (0..count).collect(),
+            free_slots: (0..count).map(Some).collect(),
+        }
+    }
+
+    /// Follows the playfield moving on the screen (same size): the cells keep what's on them
+    pub fn move_to(&mut self, playfield: Playfield) {
+        self.playfield = playfield;
+    }
+
+    fn index(&self, position: &Position) -> Option<usize> {
+        let cell = self.playfield.to_cell(position)?;
+        Some(usize::from(cell.row) * usize::from(self.playfield.get_width()) + usize::from(cell.col))
+    }
+
+    fn position(&self, index: usize) -> Position {
+        let width = usize::from(self.playfield.get_width());
+        let row = u16::try_from(index / width).unwrap_or(0);
+        let col = u16::try_from(index % width).unwrap_or(0);
+        self.playfield.to_screen(GridCell { row, col })
+    }
+
+    /// Returns what's on the cell, positions outside of the playfield are empty
+    pub fn get(&self, position: &Position) -> Occupant {
+        self.index(position).map(|index| self.cells[index]).unwrap_or_default()
+    }
+
+    pub fn is_free(&self, position: &Position) -> bool {
+        self.get(position) == Occupant::Empty
+    }
+
+    pub fn set(&mut self, position: &Position, occupant: Occupant) {
+        let Some(index) = self.index(position) else {
+            return;
+        };
+        self.cells[index] = occupant;
+        match (occupant, self.free_slots[index]) {
+            (Occupant::Empty, None) => {
+                self.free_slots[index] = Some(self.free.len());
+                self.free.push(index);
+            }
+            (Occupant::Empty, Some(_)) | (_, None) => (),
+            (_, Some(slot)) => {
+                // Moves the last free cell in the hole
+                self.free.swap_remove(slot);
+                if let Some(&moved) = self.free.get(slot) {
+                    self.free_slots[moved] = Some(slot);
+                }
+                self.free_slots[index] = None;
+            }
+        }
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Finds every cell reachable from `start` going around the hazards (the snake and the food
+    /// don't block), through the walls to the other side too if `wrap`
+    pub fn flood_fill(&self, start: &Position, wrap: bool) -> Reach {
+        let width = usize::from(self.playfield.get_width());
+        let height = usize::from(self.playfield.get_height());
+        let mut reached = vec![false; self.cells.len()];
+        let mut count = 0;
+        let mut stack: Vec<usize> = self.index(start).into_iter().collect();
+        while let Some(index) = stack.pop() {
+            if reached[index] || self.cells[index] == Occupant::Hazard {
+                continue;
+            }
+            reached[index] = true;
+            count += 1;
+
+            let (row, col) = (index / width, index % width);
+            let neighbours = [
+                if row > 0 { Some(index - width) } else if wrap { Some(index + (height - 1) * width) } else { None },
+                if row + 1 < height { Some(index + width) } else if wrap { Some(index - (height - 1) * width) } else { None },
+                if col > 0 { Some(index - 1) } else if wrap { Some(index + width - 1) } else { None },
+                if col + 1 < width { Some(index + 1) } else if wrap { Some(index - (width - 1)) } else { None },
+            ];
+            stack.extend(neighbours.into_iter().flatten().filter(|&neighbour| !reached[neighbour]));
+        }
+        Reach { playfield: self.playfield, reached, count }
+    }
+
+    /// Picks an empty cell, None if the board is full
+    pub fn random_free<R: Rng>(&self, rng: &mut R) -> Option<Position> {
+        if self.free.is_empty() {
+            return None;
+        }
+        let index = self.free[rng.random_range(0..self.free.len())];
+        Some(self.position(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::Border;
+
+    fn grid() -> OccupancyGrid {
+        OccupancyGrid::new(Playfield::new(&Border::new(1, 4, 1, 4))) // Lines 2..=3, columns 2..=3
+    }
+
+    #[test]
+    fn the_free_list_follows_the_cells() {
+        let mut grid = grid();
+        let corner = Position::new(2, 2);
+        assert_eq!(grid.free_count(), 4);
+
+        grid.set(&corner, Occupant::Snake);
+        grid.set(&corner, Occupant::Food); // Still taken
+        assert_eq!(grid.free_count(), 3);
+        assert_eq!(grid.get(&corner), Occupant::Food);
+
+        grid.set(&corner, Occupant::Empty);
+        grid.set(&corner, Occupant::Empty);
+        assert_eq!(grid.free_count(), 4);
+        assert!(grid.is_free(&corner));
+    }
+
+    #[test]
+    fn random_free_only_picks_empty_cells() {
+        let mut grid = grid();
+        let mut rng = StdRng::seed_from_u64(1);
+        for position in [Position::new(2, 2), Position::new(2, 3), Position::new(3, 3)] {
+            grid.set(&position, Occupant::Hazard);
+        }
+        for _ in 0..20 {
+            assert_eq!(grid.random_free(&mut rng), Some(Position::new(3, 2)));
+        }
+
+        grid.set(&Position::new(3, 2), Occupant::Snake);
+        assert_eq!(grid.free_count(), 0);
+        assert_eq!(grid.random_free(&mut rng), None);
+    }
+
+    #[test]
+    fn positions_outside_of_the_playfield_are_ignored() {
+        let mut grid = grid();
+        grid.set(&Position::new(1, 1), Occupant::Hazard);
+        assert_eq!(grid.free_count(), 4);
+        assert!(grid.is_free(&Position::new(9, 9)));
+    }
+
+    #[test]
+    fn flood_fill_goes_around_the_hazards() {
+        let mut grid = OccupancyGrid::new(Playfield::new(&Border::new(1, 6, 1, 4))); // Lines 2..=3, columns 2..=5
+        grid.set(&Position::new(2, 3), Occupant::Hazard);
+        grid.set(&Position::new(3, 3), Occupant::Hazard);
+        grid.set(&Position::new(3, 2), Occupant::Food); // Doesn't block
+
+        let reach = grid.flood_fill(&Position::new(2, 2), false);
+        assert_eq!(reach.count(), 2);
+        assert!(reach.contains(&Position::new(3, 2)));
+        assert!(!reach.contains(&Position::new(2, 4)));
+
+        // Through the left wall to the right side
+        let reach = grid.flood_fill(&Position::new(2, 2), true);
+        assert_eq!(reach.count(), 6);
+        assert!(reach.contains(&Position::new(3, 5)));
+        assert!(!reach.contains(&Position::new(2, 3)));
+    }
+}
@@ -0,0 +1,277 @@
+use crossterm::event::{Event, KeyCode};
+use log::{debug, error};
+
+use crate::backend::Style;
+use crate::config::{self, MAX_TICK_RATE, MIN_TICK_RATE};
+use crate::screen::Screen;
+use crate::settings::{Settings, MAX_HAZARDS, MAX_PORTAL_PAIRS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OptionItem {
+    Mode,
+    Speed,
+    SpeedCurve,
+    ArenaSize,
+    Hazards,
+    Portals,
+    Walls,
+    Theme,
+    KeyUp,
+    KeyDown,
+    KeyLeft,
+    KeyRight,
+    KeyQuit,
+    KeyPause,
+    Back,
+}
+
+impl OptionItem {
+    const ALL: [OptionItem; 15] = [
+        Self::Mode,
+        Self::Speed,
+        Self::SpeedCurve,
+        Self::ArenaSize,
+        Self::Hazards,
+        Self::Portals,
+        Self::Walls,
+        Self::Theme,
+        Self::KeyUp,
+        Self::KeyDown,
+        Self::KeyLeft,
+        Self::KeyRight,
+        Self::KeyQuit,
+        Self::KeyPause,
+        Self::Back,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Mode => "Mode",
+            Self::Speed => "Speed",
+            Self::SpeedCurve => "Speed-up",
+            Self::ArenaSize => "Arena size",
+            Self::Hazards => "Hazards",
+            Self::Portals => "Portal pairs",
+            Self::Walls => "Walls",
+            Self::Theme => "Color theme",
+            Self::KeyUp => "Key: up",
+            Self::KeyDown => "Key: down",
+            Self::KeyLeft => "Key: left",
+            Self::KeyRight => "Key: right",
+            Self::KeyQuit => "Key: quit",
+            Self::KeyPause => "Key: pause",
+            Self::Back => "Save and go back",
+        }
+    }
+
+    /// Returns the characters bound to the action, if it's a key binding item
+    fn keys<'a>(&self, settings: &'a mut Settings) -> Option<&'a mut Vec<char>> {
+        let bindings = &mut settings.key_bindings;
+        match self {
+            Self::KeyUp => Some(&mut bindings.up),
+            Self::KeyDown => Some(&mut bindings.down),
+            Self::KeyLeft => Some(&mut bindings.left),
+            Self::KeyRight => Some(&mut bindings.right),
+            Self::KeyQuit => Some(&mut bindings.quit),
+            Self::KeyPause => Some(&mut bindings.pause),
+            _ => None,
+        }
+    }
+
+    /// Binds `key` alone to the action. The action that had the key swaps: it gets the previous keys.
+    fn bind(&self, settings: &mut Settings, key: char) {
+        let Some(keys) = self.keys(settings) else {
+            return;
+        };
+        let previous = std::mem::replace(keys, vec![key]);
+        for other in Self::ALL.iter().filter(|other| *other != self) {
+            if let Some(keys) = other.keys(settings)
+                && keys.contains(&key) {
+                *keys = previous.clone();
+            }
+        }
+    }
+
+    fn value(&self, settings: &mut Settings) -> String {
+        match self {
+            Self::Mode => settings.mode.name().to_string(),
+            Self::Speed => format!("{} ms", settings.tick_rate),
+            Self::SpeedCurve => settings.speed_curve.name().to_string(),
+            Self::ArenaSize => settings.arena_size.name().to_string(),
+            Self::Hazards => settings.hazards_count.to_string(),
+            Self::Portals => settings.portal_pairs.to_string(),
+            Self::Walls => settings.walls.name().to_string(),
+            Self::Theme => settings.theme.name().to_string(),
+            Self::Back => String::new(),
+            key => key
+                .keys(settings)
+                .map(|keys| keys.iter().map(char::to_string).collect::<Vec<String>>().join(" "))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Changes the value of the item, `forward` is true when pressing right
+    fn change(&self, settings: &mut Settings, forward: bool) {
+        match self {
+            Self::Mode => {
+                settings.mode = if forward { settings.mode.next() } else { settings.mode.prev() }
+            }
+            Self::Speed => {
+                settings.tick_rate = if forward {
+                    settings.tick_rate.saturating_add(10).min(MAX_TICK_RATE)
+                } else {
+                    settings.tick_rate.saturating_sub(10).max(MIN_TICK_RATE)
+                }
+            }
+            Self::SpeedCurve => {
+                settings.speed_curve = if forward { settings.speed_curve.next() } else { settings.speed_curve.prev() }
+            }
+            Self::ArenaSize => {
+                settings.arena_size = if forward { settings.arena_size.next() } else { settings.arena_size.prev() }
+            }
+            Self::Hazards => {
+                settings.hazards_count = if forward {
+                    settings.hazards_count.saturating_add(1).min(MAX_HAZARDS)
+                } else {
+                    settings.hazards_count.saturating_sub(1)
+                }
+            }
+            Self::Portals => {
+                settings.portal_pairs = if forward {
+                    settings.portal_pairs.saturating_add(1).min(MAX_PORTAL_PAIRS)
+                } else {
+                    settings.portal_pairs.saturating_sub(1)
+                }
+            }
+            Self::Walls => {
+                settings.walls = if forward { settings.walls.next() } else { settings.walls.prev() }
+            }
+            Self::Theme => {
+                settings.theme = if forward { settings.theme.next() } else { settings.theme.prev() }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Settings screen opened from the main menu, saves the settings to the config file when leaving
+pub struct OptionsMenu {
+    settings: Settings,
+    selected: usize,
+    waiting_key: bool, // True while waiting for the key to bind to the selected action
+}
+
+impl OptionsMenu {
+    pub fn new(settings: Settings) -> Self {
+        OptionsMenu { settings, selected: 0, waiting_key: false }
+    }
+
+    pub fn get_settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn run(&mut self, screen: &mut Screen) {
+        self.draw(screen);
+
+        while let Ok(event) = Screen::get_event() {
+            let Event::Key(key) = event else {
+                continue;
+            };
+            debug!("[Options] Found key {}", key.code);
+            let item = OptionItem::ALL[self.selected];
+
+            if self.waiting_key {
+                if let KeyCode::Char(c) = key.code {
+                    item.bind(&mut self.settings, c);
+                }
+                self.waiting_key = false;
+                self.draw(screen);
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('w') => {
+                    self.selected = (self.selected + OptionItem::ALL.len() - 1) % OptionItem::ALL.len();
+                }
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('s') => {
+                    self.selected = (self.selected + 1) % OptionItem::ALL.len();
+                }
+                KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('a') => item.change(&mut self.settings, false),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('d') => item.change(&mut self.settings, true),
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    if item == OptionItem::Back {
+                        break;
+                    }
+                    if item.keys(&mut self.settings).is_some() {
+                        self.waiting_key = true;
+                    } else {
+                        item.change(&mut self.settings, true);
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => break,
+                _ => (),
+            }
+            self.draw(screen);
+        }
+
+        if let Err((setting, reason)) = config::validate(&self.settings) {
+            error!("[Options] Not saving the settings, invalid {setting}: {reason}");
+        } else if let Err(e) = self.settings.save() {
+            error!("[Options] Could not save the settings: {e}");
+        }
+    }
+
+    pub fn draw(&mut self, screen: &mut Screen) {
+        let (width, _) = screen.get_terminal_size();
+        let row_width: u16 = 32;
+        let start_col = width.saturating_sub(row_width) / 2;
+        screen.erase_screen();
+
+        let title = "Options";
+        let title_col = width.saturating_sub(title.len() as u16) / 2;
+        screen.draw_formatted_text(2, title_col, title, Style::underlined());
+
+        let mut line = 5;
+        for (index, item) in OptionItem::ALL.iter().enumerate() {
+            let selected = index == self.selected;
+            let value = if selected && self.waiting_key {
+                String::from("press a key")
+            } else {
+                item.value(&mut self.settings)
+            };
+            let text = if selected && !value.is_empty() {
+                format!("{:<16}< {} >", item.label(), value)
+            } else {
+                format!("{:<16}  {}", item.label(), value)
+            };
+            let style = if selected { Style::underlined() } else { Style::default() };
+
+            // The back item is separated from the others
+            if *item == OptionItem::Back {
+                line += 1;
+            }
+            screen.draw_formatted_text(line, start_col, text.as_str(), style);
+            line += 1;
+        }
+
+        let hint = "↑/↓ select · ←/→ change · Enter rebind · Esc save";
+        let hint_col = width.saturating_sub(hint.chars().count() as u16) / 2;
+        screen.draw_formatted_text(line + 2, hint_col, hint, Style::colored(8));
+        screen.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_a_used_key_swaps_the_bindings() {
+        let mut settings = Settings::default();
+        OptionItem::KeyUp.bind(&mut settings, 'j');
+
+        assert_eq!(settings.key_bindings.up, vec!['j']);
+        assert_eq!(settings.key_bindings.down, vec!['k', 'w']);
+        assert!(config::validate(&settings).is_ok());
+    }
+}
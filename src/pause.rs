@@ -0,0 +1,108 @@
+use crossterm::event::{Event, KeyCode};
+use log::debug;
+
+use crate::backend::Style;
+use crate::drawing::Drawer;
+use crate::screen::Screen;
+use crate::settings::KeyBindings;
+use crate::Column;
+
+const OVERLAY_WIDTH: u16 = 26;
+const OVERLAY_HEIGHT: u16 = 10;
+
+/// What the player picked on the pause screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseChoice {
+    Resume,
+    Restart,
+    Settings,
+    QuitToMenu,
+}
+
+impl PauseChoice {
+    pub const ALL: [PauseChoice; 4] = [Self::Resume, Self::Restart, Self::Settings, Self::QuitToMenu];
+
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Resume => Self::Restart,
+            Self::Restart => Self::Settings,
+            Self::Settings => Self::QuitToMenu,
+            Self::QuitToMenu => Self::Resume,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            Self::Resume => Self::QuitToMenu,
+            Self::Restart => Self::Resume,
+            Self::Settings => Self::Restart,
+            Self::QuitToMenu => Self::Settings,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Resume => "Resume",
+            Self::Restart => "Restart",
+            Self::Settings => "Settings",
+            Self::QuitToMenu => "Quit to Menu",
+        }
+    }
+}
+
+/// Box drawn over the frozen arena while the game is paused
+pub struct PauseMenu {
+    selected: PauseChoice,
+    pause_keys: Vec<char>,
+}
+
+impl Default for PauseMenu {
+    fn default() -> Self {
+        Self::new(KeyBindings::default().pause)
+    }
+}
+
+impl PauseMenu {
+    /// `pause_keys` resume the game, like Esc
+    pub fn new(pause_keys: Vec<char>) -> Self {
+        PauseMenu { selected: PauseChoice::Resume, pause_keys }
+    }
+
+    pub fn run(&mut self, screen: &mut Screen) -> PauseChoice {
+        self.draw(screen);
+        while let Ok(event) = Screen::get_event() {
+            let Event::Key(key) = event else {
+                continue;
+            };
+            debug!("[Pause] Found key {}", key.code);
+            match key.code {
+                KeyCode::Char(c) if self.pause_keys.contains(&c) => return PauseChoice::Resume,
+                KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('w') => self.selected = self.selected.prev(),
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('s') => self.selected = self.selected.next(),
+                KeyCode::Enter => return self.selected,
+                KeyCode::Esc => return PauseChoice::Resume,
+                _ => (),
+            }
+            self.draw(screen);
+        }
+        PauseChoice::Resume
+    }
+
+    pub fn draw(&self, screen: &mut Screen) {
+        let start = Drawer::draw_overlay(screen, OVERLAY_WIDTH, OVERLAY_HEIGHT);
+        let center = |text: &str| -> Column {
+            let length = u16::try_from(text.chars().count()).unwrap_or(0);
+            start.column + (OVERLAY_WIDTH + 1).saturating_sub(length) / 2
+        };
+
+        let mut line = start.line + 2;
+        screen.draw_formatted_text(line, center("PAUSED"), "PAUSED", Style::colored(crate::GREEN));
+        line += 2;
+        for choice in PauseChoice::ALL {
+            let style = if choice == self.selected { Style::underlined() } else { Style::default() };
+            screen.draw_formatted_text(line, center(choice.name()), choice.name(), style);
+            line += 1;
+        }
+        screen.flush();
+    }
+}
@@ -0,0 +1,126 @@
+use crate::settings::Glyphs;
+
+/// Ticks between the moment a power-up is taken (or goes away) and the next one showing up
+pub const POWER_UP_EVERY: u64 = 150;
+/// Ticks a power-up stays on the board before going away
+pub const POWER_UP_LIFETIME: u64 = 100;
+/// Cells around the head (in every direction) the magnet pulls food from
+pub const MAGNET_RADIUS: u16 = 5;
+/// Tick duration during slow motion, in percent of the normal one
+pub const SLOW_MOTION_PERCENT: u32 = 160;
+/// Points are multiplied by this during the multiplier
+pub const MULTIPLIER: u16 = 2;
+
+/// What the snake can pick up for a timed effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUp {
+    Ghost, // Goes through its own body and the hazards
+    Magnet, // Pulls the food in when it's close
+    SlowMotion,
+    Multiplier, // Points are worth more
+    Shield, // Survives one collision, with no time limit
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 5] = [Self::Ghost, Self::Magnet, Self::SlowMotion, Self::Multiplier, Self::Shield];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ghost => "Ghost",
+            Self::Magnet => "Magnet",
+            Self::SlowMotion => "Slow-mo",
+            Self::Multiplier => "x2",
+            Self::Shield => "Shield",
+        }
+    }
+
+    /// How many ticks the effect lasts, None until it's used
+    pub fn duration_ticks(&self) -> Option<u64> {
+        match self {
+            Self::Ghost => Some(50),
+            Self::Magnet => Some(80),
+            Self::SlowMotion => Some(60),
+            Self::Multiplier => Some(100),
+            Self::Shield => None,
+        }
+    }
+
+    pub fn glyph(&self, glyphs: &Glyphs) -> char {
+        match self {
+            Self::Ghost => glyphs.ghost,
+            Self::Magnet => glyphs.magnet,
+            Self::SlowMotion => glyphs.slow_motion,
+            Self::Multiplier => glyphs.multiplier,
+            Self::Shield => glyphs.shield,
+        }
+    }
+}
+
+/// A power-up in effect, until the tick `until` (or until it's used if None)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveEffect {
+    pub power_up: PowerUp,
+    pub until: Option<u64>,
+}
+
+/// The power-ups in effect, the last one taken on top. Taking one that's already there starts it over.
+#[derive(Debug, Clone, Default)]
+pub struct EffectStack {
+    effects: Vec<ActiveEffect>,
+}
+
+impl EffectStack {
+    /// Starts the effect of `power_up` at `tick`
+    pub fn push(&mut self, power_up: PowerUp, tick: u64) {
+        self.effects.retain(|effect| effect.power_up != power_up);
+        let until = power_up.duration_ticks().map(|ticks| tick + ticks);
+        self.effects.push(ActiveEffect { power_up, until });
+    }
+
+    pub fn is_active(&self, power_up: PowerUp) -> bool {
+        self.effects.iter().any(|effect| effect.power_up == power_up)
+    }
+
+    /// Ends the effect of `power_up`, returns false if it wasn't active
+    pub fn consume(&mut self, power_up: PowerUp) -> bool {
+        let active = self.is_active(power_up);
+        self.effects.retain(|effect| effect.power_up != power_up);
+        active
+    }
+
+    /// Ends the effects whose time is over at `tick`
+    pub fn expire(&mut self, tick: u64) {
+        self.effects.retain(|effect| effect.until.is_none_or(|until| tick < until));
+    }
+
+    /// Ticks left for `effect` at `tick`, None if it lasts until used
+    pub fn ticks_left(effect: &ActiveEffect, tick: u64) -> Option<u64> {
+        effect.until.map(|until| until.saturating_sub(tick))
+    }
+
+    pub fn get_effects(&self) -> &[ActiveEffect] {
+        &self.effects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_last_their_ticks_whatever_the_speed() {
+        let mut effects = EffectStack::default();
+        effects.push(PowerUp::Ghost, 10);
+        effects.push(PowerUp::Shield, 10);
+
+        effects.expire(59);
+        assert!(effects.is_active(PowerUp::Ghost));
+        effects.expire(60);
+        assert!(!effects.is_active(PowerUp::Ghost));
+        assert!(effects.is_active(PowerUp::Shield));
+    }
+}
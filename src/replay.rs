@@ -0,0 +1,330 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::config;
+use crate::drawing::Drawer;
+use crate::scores::today;
+use crate::screen::Screen;
+use crate::settings::Settings;
+use crate::state::{GameState, TickOutcome};
+use crate::{Border, Direction, Playfield};
+
+/// Replays kept in the replays directory, the oldest ones are deleted first
+const MAX_REPLAYS: usize = 50;
+/// Ticks played after the last input of a script, unless the snake dies before
+const SCRIPT_TAIL_TICKS: u64 = 50;
+/// Format of the replays written now. Raised whenever the same seed, settings and inputs stop giving
+/// the same game, e.g. when a feature draws more random numbers: older replays can't be played back.
+/// Replays from before the hazard behaviours have no version (0).
+const REPLAY_VERSION: u32 = 1;
+
+/// Returns `$XDG_DATA_HOME/snake/replays`
+pub fn replays_dir() -> Option<PathBuf> {
+    Some(config::data_dir()?.join("replays"))
+}
+
+/// Direction given to the game on a tick, written as e.g. `12U` (tick 12, up)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedInput {
+    pub tick: u64,
+    pub direction: Direction,
+}
+
+impl Display for RecordedInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = match self.direction {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        };
+        write!(f, "{}{letter}", self.tick)
+    }
+}
+
+impl FromStr for RecordedInput {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid input '{text}'");
+        let direction = match text.chars().last() {
+            Some('U') => Direction::Up,
+            Some('D') => Direction::Down,
+            Some('L') => Direction::Left,
+            Some('R') => Direction::Right,
+            _ => return Err(invalid()),
+        };
+        let tick = text[..text.len() - 1].parse().map_err(|_| invalid())?;
+        Ok(RecordedInput { tick, direction })
+    }
+}
+
+/// Inputs are saved as a single string, e.g. `"3L 10U 15R"`
+fn serialize_inputs<S: Serializer>(inputs: &[RecordedInput], serializer: S) -> Result<S::Ok, S::Error> {
+    let text = inputs.iter().map(RecordedInput::to_string).collect::<Vec<String>>().join(" ");
+    serializer.serialize_str(&text)
+}
+
+fn deserialize_inputs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<RecordedInput>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    text.split_whitespace().map(|input| input.parse().map_err(serde::de::Error::custom)).collect()
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    NoDataDir,
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// The file parsed but can't be played: explanation
+    Invalid(PathBuf, String),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDataDir => write!(f, "could not find the data directory, set $XDG_DATA_HOME or $HOME"),
+            Self::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            Self::Parse(path, e) => write!(f, "{}: {e}", path.display()),
+            Self::Serialize(e) => write!(f, "could not write the replay: {e}"),
+            Self::Invalid(path, reason) => write!(f, "{}: {reason}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Everything needed to play a game again: the engine is deterministic, so the seed, the rules
+/// and the inputs give back the exact same game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    #[serde(default)]
+    pub version: u32,
+    pub date: String, // YYYY-MM-DD
+    #[serde(serialize_with = "config::serialize_seed", deserialize_with = "config::deserialize_seed")]
+    pub seed: u64,
+    pub score: u16,
+    pub ticks: u64, // Ticks played, the last one is usually the death
+    pub border: Border,
+    #[serde(serialize_with = "serialize_inputs", deserialize_with = "deserialize_inputs")]
+    pub inputs: Vec<RecordedInput>,
+    pub settings: Settings, // With the mode rules already applied
+}
+
+impl Replay {
+    /// Starts recording the game generated by `GameState::new(border, settings, Some(seed))`
+    pub fn new(border: Border, settings: &Settings, seed: u64) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            date: today(),
+            seed,
+            score: 0,
+            ticks: 0,
+            border,
+            inputs: Vec::new(),
+            settings: settings.clone(),
+        }
+    }
+
+    /// Records the input given to the game on `tick`
+    pub fn record(&mut self, tick: u64, direction: Direction) {
+        self.inputs.push(RecordedInput { tick, direction });
+    }
+
+    /// Creates the replay of a game played with scripted inputs, written like the `inputs` of a replay
+    /// file (e.g. `3L 10U 15R`, `#` starts a comment). The game stops when the snake dies or fills the board,
+    /// or `SCRIPT_TAIL_TICKS` after the last input.
+    pub fn from_script(border: Border, settings: &Settings, seed: u64, script: &str) -> Result<Self, String> {
+        let mut replay = Self::new(border, settings, seed);
+        for line in script.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            for input in line.split_whitespace() {
+                let input: RecordedInput = input.parse()?;
+                if replay.inputs.last().is_some_and(|last| last.tick >= input.tick) {
+                    return Err(format!("input '{input}' is not after the previous one"));
+                }
+                replay.inputs.push(input);
+            }
+        }
+
+        let last_tick = replay.inputs.last().map(|input| input.tick).unwrap_or(0);
+        let mut state = replay.start();
+        while state.get_tick() < last_tick + SCRIPT_TAIL_TICKS {
+            if state.step(replay.input_at(state.get_tick() + 1)).is_over() {
+                break;
+            }
+        }
+        replay.finish(&state);
+        Ok(replay)
+    }
+
+    /// Returns the input given on `tick`, if any
+    pub fn input_at(&self, tick: u64) -> Option<Direction> {
+        let index = self.inputs.binary_search_by_key(&tick, |input| input.tick).ok()?;
+        Some(self.inputs[index].direction)
+    }
+
+    /// Records how far the game went
+    pub fn finish(&mut self, state: &GameState) {
+        self.score = state.get_score();
+        self.ticks = state.get_tick();
+    }
+
+    /// Creates the game from the beginning
+    pub fn start(&self) -> GameState {
+        GameState::new(self.border, &self.settings, Some(self.seed))
+    }
+
+    /// Plays the next tick of `state` with the recorded input
+    pub fn step(&self, state: &mut GameState) -> TickOutcome {
+        state.step(self.input_at(state.get_tick() + 1))
+    }
+
+    /// Plays the next tick and draws what changed on `screen`. Returns how long the tick lasts at
+    /// the recorded speed, with what happened.
+    pub fn play_tick(&self, screen: &mut Screen, state: &mut GameState) -> (Duration, TickOutcome) {
+        let settings = &self.settings;
+        let duration = state.tick_duration();
+        Drawer::erase_moving(screen, state);
+        let outcome = self.step(state);
+        Drawer::draw_moving(screen, state, outcome, &settings.palette(), &settings.glyphs);
+        screen.flush();
+        (duration, outcome)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let text = fs::read_to_string(path).map_err(|e| ReplayError::Io(path.to_path_buf(), e))?;
+        let replay: Replay = toml::from_str(&text).map_err(|e| ReplayError::Parse(path.to_path_buf(), e))?;
+        if replay.version != REPLAY_VERSION {
+            let reason = format!("replay format {} can't be played back, this version plays format {REPLAY_VERSION}", replay.version);
+            return Err(ReplayError::Invalid(path.to_path_buf(), reason));
+        }
+        config::validate(&replay.settings)
+            .map_err(|(setting, reason)| ReplayError::Invalid(path.to_path_buf(), format!("invalid `settings.{setting}`: {reason}")))?;
+        let playfield = Playfield::new(&replay.border);
+        if replay.border.end_col <= replay.border.start_col || replay.border.end_line <= replay.border.start_line
+            || playfield.get_width() < crate::MIN_PLAYFIELD_SIZE || playfield.get_height() < crate::MIN_PLAYFIELD_SIZE {
+            return Err(ReplayError::Invalid(path.to_path_buf(), String::from("the arena is too small")));
+        }
+        if replay.inputs.windows(2).any(|inputs| inputs[0].tick >= inputs[1].tick) {
+            return Err(ReplayError::Invalid(path.to_path_buf(), String::from("the inputs are not in tick order")));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| ReplayError::Io(dir.to_path_buf(), e))?;
+        }
+        let text = toml::to_string(self).map_err(ReplayError::Serialize)?;
+        fs::write(path, text).map_err(|e| ReplayError::Io(path.to_path_buf(), e))?;
+        info!("[Replay] Saved to {}", path.display());
+        Ok(())
+    }
+
+    /// Saves into the replays directory, named after the current time, and returns the file path
+    pub fn save_new(&self) -> Result<PathBuf, ReplayError> {
+        let dir = replays_dir().ok_or(ReplayError::NoDataDir)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+        let path = dir.join(format!("replay-{now}.toml"));
+        self.save(&path)?;
+        Self::prune(&dir);
+        Ok(path)
+    }
+
+    /// Deletes the oldest replays, keeping the last `MAX_REPLAYS`
+    fn prune(dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut replays: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("replay-")))
+            .collect();
+        if replays.len() <= MAX_REPLAYS {
+            return;
+        }
+        // Same length names, so the name order is the time order
+        replays.sort();
+        for path in &replays[..replays.len() - MAX_REPLAYS] {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("[Replay] Could not delete {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+    use crate::state::TickOutcome;
+
+    /// Lets the bot play a recorded game, for `ticks` at most
+    fn record_game(ticks: u64) -> (Replay, GameState) {
+        let border = Border::new(1, 30, 2, 14);
+        let settings = Settings::default();
+        let mut state = GameState::new(border, &settings, Some(7));
+        let mut replay = Replay::new(border, &settings, state.get_seed());
+        for _ in 0..ticks {
+            let direction = Bot::next_direction(&state);
+            if direction != state.get_snake().get_direction() {
+                replay.record(state.get_tick() + 1, direction);
+            }
+            if let TickOutcome::Died(_) = state.step(Some(direction)) {
+                break;
+            }
+        }
+        replay.finish(&state);
+        (replay, state)
+    }
+
+    #[test]
+    fn a_replay_plays_the_recorded_game_again() {
+        let (replay, recorded) = record_game(300);
+        let replay: Replay = toml::from_str(&toml::to_string(&replay).unwrap()).unwrap();
+        assert!(!replay.inputs.is_empty());
+
+        let mut state = replay.start();
+        let mut inputs = replay.inputs.iter().peekable();
+        while state.get_tick() < replay.ticks {
+            let input = inputs.next_if(|input| input.tick == state.get_tick() + 1).map(|input| input.direction);
+            state.step(input);
+        }
+
+        assert_eq!(state.get_score(), replay.score);
+        assert_eq!(state.get_score(), recorded.get_score());
+        assert_eq!(state.get_snake().get_positions(), recorded.get_snake().get_positions());
+        assert_eq!(state.get_food().position, recorded.get_food().position);
+    }
+
+    #[test]
+    fn recorded_inputs_are_written_as_tick_and_letter() {
+        let inputs: Vec<RecordedInput> = "3L 10U".split_whitespace().map(|input| input.parse().unwrap()).collect();
+        assert_eq!(inputs, [RecordedInput { tick: 3, direction: Direction::Left }, RecordedInput { tick: 10, direction: Direction::Up }]);
+        assert_eq!(inputs[1].to_string(), "10U");
+        assert!("12X".parse::<RecordedInput>().is_err());
+        assert!("U".parse::<RecordedInput>().is_err());
+    }
+
+    #[test]
+    fn load_rejects_replays_without_a_version() {
+        let replay = Replay::new(Border::new(1, 22, 1, 12), &Settings::default(), 7);
+        let path = std::env::temp_dir().join(format!("snake-replay-{}.toml", std::process::id()));
+        replay.save(&path).unwrap();
+        assert_eq!(Replay::load(&path).unwrap(), replay);
+
+        let text = fs::read_to_string(&path).unwrap().replace("version = 1\n", "");
+        fs::write(&path, text).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(loaded, Err(ReplayError::Invalid(..))));
+    }
+}
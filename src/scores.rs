@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::settings::GameMode;
+use crate::{Height, Width};
+
+/// Entries kept for every mode and arena size
+pub const TABLE_SIZE: usize = 10;
+
+/// Returns `$XDG_DATA_HOME/snake/scores.toml`
+pub fn scores_path() -> Option<PathBuf> {
+    Some(config::data_dir()?.join("scores.toml"))
+}
+
+/// Returns today's date as YYYY-MM-DD (UTC)
+pub fn today() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let days = (seconds / 86_400) as i64;
+
+    // Converts days since 1970-01-01 to a civil date: https://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats seconds as m:ss
+pub fn format_duration(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: u16,
+    pub length: u16,
+    pub duration: u64, // Seconds
+    #[serde(serialize_with = "config::serialize_seed", deserialize_with = "config::deserialize_seed")]
+    pub seed: u64,
+    pub date: String, // YYYY-MM-DD
+}
+
+/// Best runs, in a separate table for every mode and arena size
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(default)]
+    tables: BTreeMap<String, Vec<ScoreEntry>>,
+}
+
+impl HighScores {
+    /// Name of the table for a mode and arena size, e.g. `classic 60x16`
+    pub fn table_name(mode: GameMode, width: Width, height: Height) -> String {
+        format!("{} {width}x{height}", mode.name().to_lowercase())
+    }
+
+    /// Reads the scores file, starting from an empty table if it's missing or invalid
+    pub fn load() -> Self {
+        let Some(path) = scores_path() else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
+                warn!("[Scores] Ignoring invalid {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = scores_path().ok_or_else(|| io::Error::other("could not find the data directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(&path, text)?;
+        info!("[Scores] Saved to {}", path.display());
+        Ok(())
+    }
+
+    pub fn get_table_names(&self) -> Vec<&String> {
+        self.tables.keys().collect()
+    }
+
+    pub fn get_table(&self, table: &str) -> &[ScoreEntry] {
+        self.tables.get(table).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns true if the score is good enough to enter the table
+    pub fn qualifies(&self, table: &str, score: u16) -> bool {
+        let entries = self.get_table(table);
+        score > 0 && (entries.len() < TABLE_SIZE || entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Adds the entry if it qualifies, returning its rank (starting from 0)
+    pub fn insert(&mut self, table: &str, entry: ScoreEntry) -> Option<usize> {
+        if !self.qualifies(table, entry.score) {
+            return None;
+        }
+        let entries = self.tables.entry(table.to_string()).or_default();
+        // Equal scores: the older run stays in front
+        let rank = entries.iter().position(|other| entry.score > other.score).unwrap_or(entries.len());
+        entries.insert(rank, entry);
+        entries.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u16) -> ScoreEntry {
+        ScoreEntry { name: name.to_string(), score, length: score + 4, duration: 60, seed: 42, date: String::from("2024-01-01") }
+    }
+
+    fn names(scores: &HighScores, table: &str) -> Vec<String> {
+        scores.get_table(table).iter().map(|entry| entry.name.clone()).collect()
+    }
+
+    #[test]
+    fn insert_keeps_the_table_sorted() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.insert("classic 60x16", entry("a", 5)), Some(0));
+        assert_eq!(scores.insert("classic 60x16", entry("b", 9)), Some(0));
+        assert_eq!(scores.insert("classic 60x16", entry("c", 7)), Some(1));
+        assert_eq!(scores.insert("classic 60x16", entry("d", 7)), Some(2)); // Behind the older run
+
+        assert_eq!(names(&scores, "classic 60x16"), ["b", "c", "d", "a"]);
+        assert!(scores.get_table("zen 60x16").is_empty());
+    }
+
+    #[test]
+    fn insert_drops_runs_that_dont_make_the_table() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.insert("classic 60x16", entry("zero", 0)), None);
+        for score in 1..=TABLE_SIZE as u16 {
+            scores.insert("classic 60x16", entry("run", score));
+        }
+
+        assert_eq!(scores.insert("classic 60x16", entry("low", 1)), None);
+        assert_eq!(scores.insert("classic 60x16", entry("high", 20)), Some(0));
+        assert_eq!(scores.get_table("classic 60x16").len(), TABLE_SIZE);
+        assert_eq!(scores.get_table("classic 60x16").last().map(|entry| entry.score), Some(2));
+    }
+
+    #[test]
+    fn scores_and_any_seed_survive_a_round_trip_through_toml() {
+        let mut scores = HighScores::default();
+        scores.insert(&HighScores::table_name(GameMode::Zen, 60, 16), ScoreEntry { seed: u64::MAX, ..entry("a", 5) });
+        let text = toml::to_string_pretty(&scores).unwrap();
+
+        assert_eq!(toml::from_str::<HighScores>(&text).unwrap(), scores);
+        assert_eq!(scores.get_table_names(), [&String::from("zen 60x16")]);
+    }
+
+    #[test]
+    fn format_duration_pads_the_seconds() {
+        assert_eq!(format_duration(65), "1:05");
+        assert_eq!(format_duration(600), "10:00");
+    }
+}
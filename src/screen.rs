@@ -1,64 +1,78 @@
-use std::io::{self, Write};
 use std::time::Duration;
 
-use crossterm::event::{poll, read, Event};
-use crossterm::terminal::{enable_raw_mode};
+use crossterm::event::{poll, read, Event, KeyCode};
+use log::info;
 
-use crate::cursor::{Cursor};
-use crate::{Column, Height, Line, WHITE, Position, Width, ESC};
+use crate::backend::{AnsiBackend, RenderBackend, Style};
+use crate::cursor::Cursor;
+use crate::frame::FrameBuffer;
+use crate::{Column, Height, Line, Width, RED, WHITE};
 
-#[derive(Default, Debug)]
+/// Draws into a back buffer, [`Screen::flush`] sends to the backend only the cells that changed since
+/// the previous flush (kept in the front buffer)
 pub struct Screen {
     pub cursor: Cursor,
-    width: Line,
-    height: Column,
+    backend: Box<dyn RenderBackend>,
+    back: FrameBuffer,
+    front: FrameBuffer,
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[allow(dead_code)]
 impl Screen {
     pub fn new() -> Self {
-        Screen { cursor: Cursor::new(), height: 0, width: 0 }
+        Self::with_backend(Box::new(AnsiBackend::new()))
     }
 
-    pub fn from(cursor_position: Position, height: Height, width: Width) -> Self {
-        let cursor = Cursor::from(cursor_position, false);
-        Screen { cursor, height, width }
+    pub fn with_backend(backend: Box<dyn RenderBackend>) -> Self {
+        let (width, height) = backend.size();
+        Screen {
+            cursor: Cursor::new(),
+            backend,
+            back: FrameBuffer::new(width, height),
+            front: FrameBuffer::new(width, height),
+        }
     }
 
     pub fn init(&mut self) {
-        let _ = enable_raw_mode(); // Hides input keys, input processed without enter, etc.
+        self.backend.init();
 
-        Self::erase_screen();
+        self.erase_screen();
+        self.redraw();
         self.hide_cursor();
-        self.update_terminal_size();
-        Self::flush();
+        self.flush();
     }
 
     pub fn draw(&mut self, line: Line, column: Column, character: char) {
         self.draw_colored(line, column, character, WHITE);
     }
 
-    // https://gist.github.com/ConnerWill/d4b6c776b509add763e17f9f113fd25b#colors--graphics-mode
     // 256 colors (8 bit)
     pub fn draw_colored(&mut self, line: Line, column: Column, character: char, color: u16) {
         self.cursor.jump(line, column);
-        print!("{ESC}[38;5;{color}m{character}");
-        Self::reset_style();
+        self.print_styled(character.encode_utf8(&mut [0; 4]), Style::colored(color));
     }
 
-    pub fn draw_formatted_text(&mut self, line: Line, column: Column, text: &str, style: u16) {
+    pub fn draw_formatted_text(&mut self, line: Line, column: Column, text: &str, style: Style) {
         self.cursor.jump(line, column);
-        print!("{ESC}[{style}m{text}");
-        Self::reset_style();
+        self.print_styled(text, style);
     }
 
-    pub fn reset_style() {
-        print!("{ESC}[0m"); // Resets modes (color)
+    /// Writes unstyled text at the cursor position, moving the cursor after it
+    pub fn print(&mut self, text: &str) {
+        self.print_styled(text, Style::default());
     }
 
-    pub fn reset_style_at(&mut self, position: &Position) {
-        self.cursor.jump_to_position(*position);
-        print!("{ESC}[0m"); // Resets modes (color)
+    fn print_styled(&mut self, text: &str, style: Style) {
+        let position = *self.cursor.get_position();
+        self.back.write(position.line, position.column, text, style);
+        let length = u16::try_from(text.chars().count()).unwrap_or(u16::MAX);
+        self.cursor.jump_to_col(position.column.saturating_add(length));
     }
 
     pub fn delete(&mut self, line: Line, column: Column) {
@@ -67,23 +81,36 @@ impl Screen {
 
     /// Returns width and height as tuple
     pub fn get_terminal_size(&self) -> (u16, u16) {
-        (self.width, self.height)
+        self.backend.size()
     }
 
-    fn update_terminal_size(&mut self) {
-        if let Some((width, height)) = terminal_size::terminal_size() {
-            self.width = width.0;
-            self.height = height.0;
+    /// Reads the terminal size again and, if it changed, resizes the buffers and clears the real
+    /// screen: everything has to be drawn again. Returns true if it changed.
+    pub fn update_terminal_size(&mut self) -> bool {
+        let (width, height) = self.backend.refresh_size();
+        if (width, height) == self.back.get_size() {
+            return false;
         }
+        info!("[Screen] Resized to {width}x{height}");
+        self.back = FrameBuffer::new(width, height);
+        self.front = FrameBuffer::new(width, height);
+        self.redraw();
+        true
+    }
+
+    pub fn erase_screen(&mut self) {
+        self.back.clear();
     }
 
-    pub fn erase_screen() {
-        print!("{ESC}[2J");
+    /// Clears the real screen, so the next flush sends the whole back buffer
+    pub fn redraw(&mut self) {
+        self.backend.clear();
+        self.front.clear();
     }
 
-    /// Returns true if an event is available to be read
-    pub fn poll_event() -> std::io::Result<bool> {
-        poll(Duration::from_millis(100))
+    /// Returns true if an event is available to be read, waiting at most `timeout` for one
+    pub fn poll_event(timeout: Duration) -> std::io::Result<bool> {
+        poll(timeout)
     }
 
     /// Read event (if available) or block until available
@@ -91,15 +118,55 @@ impl Screen {
         read()
     }
 
+    /// Shows a notice until the terminal is at least `needed` (width, height) big, returns false if
+    /// a key for which `is_quit` is true is pressed instead
+    pub fn wait_for_room(&mut self, needed: (Width, Height), is_quit: impl Fn(KeyCode) -> bool) -> bool {
+        loop {
+            self.update_terminal_size();
+            let (width, height) = self.get_terminal_size();
+            if width >= needed.0 && height >= needed.1 {
+                return true;
+            }
+            info!("[Screen] Too small: {width}x{height}, needs {}x{}", needed.0, needed.1);
+            self.erase_screen();
+            let lines = [
+                String::from("Terminal too small"),
+                format!("{width}x{height}, needs {}x{}", needed.0, needed.1),
+                String::from("Resize it, or Esc to leave"),
+            ];
+            for (line, text) in (1..).zip(lines) {
+                self.draw_formatted_text(line, 1, text.as_str(), Style::colored(RED));
+            }
+            self.flush();
+
+            match Self::get_event() {
+                Ok(Event::Key(key)) if is_quit(key.code) => return false,
+                Ok(_) => (),
+                Err(_) => return false,
+            }
+        }
+    }
+
     pub fn hide_cursor (&mut self) {
         self.cursor.hide();
+        self.backend.set_cursor_visible(false);
     }
 
     pub fn show_cursor (&mut self) {
         self.cursor.show();
+        self.backend.set_cursor_visible(true);
     }
 
-    pub fn flush() {
-        io::stdout().flush().unwrap();
+    /// Makes the terminal send [`Event::FocusGained`] and [`Event::FocusLost`]
+    pub fn set_focus_reporting(&mut self, enabled: bool) {
+        self.backend.set_focus_reporting(enabled);
+    }
+
+    pub fn flush(&mut self) {
+        for run in self.back.diff(&self.front) {
+            self.backend.write(run.line, run.column, run.text.as_str(), run.style);
+        }
+        self.front.clone_from(&self.back);
+        self.backend.flush();
     }
 }
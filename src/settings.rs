@@ -0,0 +1,562 @@
+use crossterm::event::KeyCode;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::SpeedCurve;
+use crate::config::{self, ConfigError};
+use crate::food::ModeFoodWeights;
+use crate::hazard::ModeHazardRules;
+use crate::{Border, Direction, Height, Width, GREEN, RED, WHITE};
+
+/// The options menu and the config file don't allow more hazards than this
+pub const MAX_HAZARDS: u8 = 99;
+/// Nor more pairs of portals than this
+pub const MAX_PORTAL_PAIRS: u8 = 4;
+
+/// Space left between the terminal edges and the border when the arena fills the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Margins {
+    pub horizontal: u16,
+    pub vertical: u16,
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Margins { horizontal: 10, vertical: 4 }
+    }
+}
+
+impl Margins {
+    /// Smallest terminal with room for the border and the smallest playfield inside the margins
+    pub fn min_terminal_size(&self) -> (Width, Height) {
+        (
+            2 * self.horizontal + crate::MIN_PLAYFIELD_SIZE + 1,
+            2 * self.vertical + crate::MIN_PLAYFIELD_SIZE + 1,
+        )
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArenaSize {
+    Small,
+    Medium,
+    Large,
+    #[default]
+    Fill, // Whole terminal, except for the margins
+}
+
+impl ArenaSize {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Small => Self::Medium,
+            Self::Medium => Self::Large,
+            Self::Large => Self::Fill,
+            Self::Fill => Self::Small,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            Self::Small => Self::Fill,
+            Self::Medium => Self::Small,
+            Self::Large => Self::Medium,
+            Self::Fill => Self::Large,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Small => "Small",
+            Self::Medium => "Medium",
+            Self::Large => "Large",
+            Self::Fill => "Fill",
+        }
+    }
+
+    /// Returns the border of the arena, centered in a terminal of the given size.
+    /// Sizes bigger than the terminal shrink to fill it, leaving the margins.
+    pub fn border(&self, width: Width, height: Height, margins: &Margins) -> Border {
+        let fill = Border::new(
+            margins.horizontal,
+            width.saturating_sub(margins.horizontal),
+            margins.vertical,
+            height.saturating_sub(margins.vertical),
+        );
+        let (arena_width, arena_height) = match self {
+            Self::Small => (30, 12),
+            Self::Medium => (50, 18),
+            Self::Large => (70, 24),
+            Self::Fill => return fill,
+        };
+        if arena_width > fill.end_col.saturating_sub(fill.start_col)
+            || arena_height > fill.end_line.saturating_sub(fill.start_line) {
+            return fill;
+        }
+
+        let start_col = (width - arena_width) / 2;
+        let start_line = (height - arena_height) / 2;
+        Border::new(start_col, start_col + arena_width, start_line, start_line + arena_height)
+    }
+}
+
+/// Rules of the game, applied over the other settings
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    #[default]
+    Classic, // Plays with the settings as they are
+    Zen, // No hazards and no walls
+    Hardcore, // Solid walls, and the snake speeds up as it eats
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [Self::Classic, Self::Zen, Self::Hardcore];
+
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Classic => Self::Zen,
+            Self::Zen => Self::Hardcore,
+            Self::Hardcore => Self::Classic,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            Self::Classic => Self::Hardcore,
+            Self::Zen => Self::Classic,
+            Self::Hardcore => Self::Zen,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Classic => "Classic",
+            Self::Zen => "Zen",
+            Self::Hardcore => "Hardcore",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the settings the game is actually played with
+    pub fn apply(&self, mut settings: Settings) -> Settings {
+        match self {
+            Self::Classic => (),
+            Self::Zen => {
+                settings.hazards_count = 0;
+                settings.walls = WallBehaviour::Wrap;
+            }
+            Self::Hardcore => {
+                settings.walls = WallBehaviour::Solid;
+                if settings.speed_curve == SpeedCurve::Constant {
+                    settings.speed_curve = SpeedCurve::Capped { step: 3, min: 40 };
+                }
+            }
+        }
+        settings
+    }
+}
+
+/// What happens when the snake reaches the border
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WallBehaviour {
+    Wrap, // Comes out of the opposite side
+    #[default]
+    Solid, // Hitting the wall kills the snake
+    Bounce, // Turns along the wall, dies only if it can't (in a corner)
+}
+
+impl WallBehaviour {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Wrap => Self::Solid,
+            Self::Solid => Self::Bounce,
+            Self::Bounce => Self::Wrap,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            Self::Wrap => Self::Bounce,
+            Self::Solid => Self::Wrap,
+            Self::Bounce => Self::Solid,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Wrap => "Wrap",
+            Self::Solid => "Solid",
+            Self::Bounce => "Bounce",
+        }
+    }
+}
+
+/// 256 colors (8 bit) used to draw the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub snake: u16,
+    pub food: u16,
+    pub hazard: u16,
+    pub golden: u16,
+    pub rotten: u16,
+    pub speed: u16,
+    pub slow: u16,
+    pub expiring: u16,
+    pub power_up: u16,
+    pub portal: u16,
+}
+
+/// Colors replacing the ones of the theme
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snake: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub food: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hazard: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub golden: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotten: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiring: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_up: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub portal: Option<u16>,
+}
+
+impl ColorOverrides {
+    /// Returns every color with the name of its setting
+    pub fn named(&self) -> [(&'static str, Option<u16>); 10] {
+        [
+            ("colors.snake", self.snake),
+            ("colors.food", self.food),
+            ("colors.hazard", self.hazard),
+            ("colors.golden", self.golden),
+            ("colors.rotten", self.rotten),
+            ("colors.speed", self.speed),
+            ("colors.slow", self.slow),
+            ("colors.expiring", self.expiring),
+            ("colors.power_up", self.power_up),
+            ("colors.portal", self.portal),
+        ]
+    }
+}
+
+/// Characters used to draw the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Glyphs {
+    pub head: char,
+    pub body: char, // Segment without a direction yet
+    pub horizontal: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+    pub food: char,
+    pub golden: char,
+    pub rotten: char,
+    pub speed: char,
+    pub slow: char,
+    pub expiring: char,
+    pub ghost: char,
+    pub magnet: char,
+    pub slow_motion: char,
+    pub multiplier: char,
+    pub shield: char,
+    pub portal: char,
+    pub hazard: char,
+    pub warning: char, // Where a blinking hazard is coming back
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Glyphs {
+            head: '◉',
+            body: '⬤',
+            horizontal: '━',
+            vertical: '┃',
+            top_left: '┏',
+            top_right: '┓',
+            bottom_left: '┗',
+            bottom_right: '┛',
+            food: '✿',
+            golden: '★',
+            rotten: '✗',
+            speed: '»',
+            slow: '«',
+            expiring: '◷',
+            ghost: '◌',
+            magnet: '∩',
+            slow_motion: '≈',
+            multiplier: '×',
+            shield: '◈',
+            portal: '◎',
+            hazard: '☠',
+            warning: '!',
+        }
+    }
+}
+
+impl Glyphs {
+    /// Returns every glyph with the name of its setting
+    pub fn named(&self) -> [(&'static str, char); 22] {
+        [
+            ("glyphs.head", self.head),
+            ("glyphs.body", self.body),
+            ("glyphs.horizontal", self.horizontal),
+            ("glyphs.vertical", self.vertical),
+            ("glyphs.top_left", self.top_left),
+            ("glyphs.top_right", self.top_right),
+            ("glyphs.bottom_left", self.bottom_left),
+            ("glyphs.bottom_right", self.bottom_right),
+            ("glyphs.food", self.food),
+            ("glyphs.golden", self.golden),
+            ("glyphs.rotten", self.rotten),
+            ("glyphs.speed", self.speed),
+            ("glyphs.slow", self.slow),
+            ("glyphs.expiring", self.expiring),
+            ("glyphs.ghost", self.ghost),
+            ("glyphs.magnet", self.magnet),
+            ("glyphs.slow_motion", self.slow_motion),
+            ("glyphs.multiplier", self.multiplier),
+            ("glyphs.shield", self.shield),
+            ("glyphs.portal", self.portal),
+            ("glyphs.hazard", self.hazard),
+            ("glyphs.warning", self.warning),
+        ]
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Classic,
+    Ocean,
+    Mono,
+}
+
+impl Theme {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Classic => Self::Ocean,
+            Self::Ocean => Self::Mono,
+            Self::Mono => Self::Classic,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            Self::Classic => Self::Mono,
+            Self::Ocean => Self::Classic,
+            Self::Mono => Self::Ocean,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Classic => "Classic",
+            Self::Ocean => "Ocean",
+            Self::Mono => "Mono",
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        match self {
+            Self::Classic => Palette { snake: GREEN, food: 26, hazard: RED, golden: 220, rotten: 94, speed: 51, slow: 135, expiring: 208, power_up: 213, portal: 39 },
+            Self::Ocean => Palette { snake: 45, food: 220, hazard: 201, golden: 226, rotten: 101, speed: 87, slow: 63, expiring: 214, power_up: 118, portal: 231 },
+            Self::Mono => Palette { snake: WHITE, food: 250, hazard: 240, golden: 255, rotten: 238, speed: 252, slow: 244, expiring: 248, power_up: 231, portal: 252 },
+        }
+    }
+}
+
+/// Characters bound to every action. Arrow keys and Esc always work too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub up: Vec<char>,
+    pub down: Vec<char>,
+    pub left: Vec<char>,
+    pub right: Vec<char>,
+    pub quit: Vec<char>,
+    pub pause: Vec<char>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            up: vec!['k', 'w'],
+            down: vec!['j', 's'],
+            left: vec!['h', 'a'],
+            right: vec!['l', 'd'],
+            quit: vec!['q'],
+            pause: vec!['p', ' '],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn direction(&self, code: KeyCode) -> Option<Direction> {
+        match code {
+            KeyCode::Up => Some(Direction::Up),
+            KeyCode::Down => Some(Direction::Down),
+            KeyCode::Left => Some(Direction::Left),
+            KeyCode::Right => Some(Direction::Right),
+            KeyCode::Char(c) if self.up.contains(&c) => Some(Direction::Up),
+            KeyCode::Char(c) if self.down.contains(&c) => Some(Direction::Down),
+            KeyCode::Char(c) if self.left.contains(&c) => Some(Direction::Left),
+            KeyCode::Char(c) if self.right.contains(&c) => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    /// Returns the keys of every action with the name of its setting
+    pub fn named(&self) -> [(&'static str, &Vec<char>); 6] {
+        [
+            ("key_bindings.up", &self.up),
+            ("key_bindings.down", &self.down),
+            ("key_bindings.left", &self.left),
+            ("key_bindings.right", &self.right),
+            ("key_bindings.quit", &self.quit),
+            ("key_bindings.pause", &self.pause),
+        ]
+    }
+
+    pub fn is_pause(&self, code: KeyCode) -> bool {
+        matches!(code, KeyCode::Char(c) if self.pause.contains(&c))
+    }
+
+    pub fn is_quit(&self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Esc => true,
+            KeyCode::Char(c) => self.quit.contains(&c),
+            _ => false,
+        }
+    }
+}
+
+/// Options chosen by the player before starting a game, saved in the config file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub mode: GameMode,
+    pub tick_rate: u64, // Milliseconds between two moves at the start of the game
+    pub speed_curve: SpeedCurve,
+    pub arena_size: ArenaSize,
+    pub margins: Margins,
+    pub hazards_count: u8,
+    pub hazard_rules: ModeHazardRules,
+    pub food_weights: ModeFoodWeights,
+    pub portal_pairs: u8,
+    pub start_length: u16, // Nodes of the snake, head included
+    pub walls: WallBehaviour,
+    pub input_buffer: u8, // Turns kept when typed faster than the snake moves
+    pub theme: Theme,
+    pub colors: ColorOverrides,
+    pub glyphs: Glyphs,
+    pub key_bindings: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: GameMode::Classic,
+            tick_rate: 100,
+            speed_curve: SpeedCurve::Constant,
+            arena_size: ArenaSize::Fill,
+            margins: Margins::default(),
+            hazards_count: 20,
+            hazard_rules: ModeHazardRules::default(),
+            food_weights: ModeFoodWeights::default(),
+            portal_pairs: 1,
+            start_length: 4,
+            walls: WallBehaviour::Solid,
+            input_buffer: 3,
+            theme: Theme::Classic,
+            colors: ColorOverrides::default(),
+            glyphs: Glyphs::default(),
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings from the config file, using the defaults if it's invalid
+    pub fn load() -> Self {
+        config::load().unwrap_or_else(|e| {
+            warn!("[Settings] Using the defaults, {e}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<(), ConfigError> {
+        config::save(self)
+    }
+
+    /// Returns the colors of the theme, replaced by the ones set in `colors`
+    pub fn palette(&self) -> Palette {
+        let palette = self.theme.palette();
+        Palette {
+            snake: self.colors.snake.unwrap_or(palette.snake),
+            food: self.colors.food.unwrap_or(palette.food),
+            hazard: self.colors.hazard.unwrap_or(palette.hazard),
+            golden: self.colors.golden.unwrap_or(palette.golden),
+            rotten: self.colors.rotten.unwrap_or(palette.rotten),
+            speed: self.colors.speed.unwrap_or(palette.speed),
+            slow: self.colors.slow.unwrap_or(palette.slow),
+            expiring: self.colors.expiring.unwrap_or(palette.expiring),
+            power_up: self.colors.power_up.unwrap_or(palette.power_up),
+            portal: self.colors.portal.unwrap_or(palette.portal),
+        }
+    }
+
+    /// Returns the border of the arena in a terminal of the given size
+    pub fn border(&self, width: Width, height: Height) -> Border {
+        self.arena_size.border(width, height, &self.margins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::FoodWeights;
+    use crate::hazard::HazardRules;
+
+    #[test]
+    fn partial_mode_tables_keep_the_mode_defaults() {
+        let settings: Settings = toml::from_str(indoc::indoc! {"
+            [hazard_rules.hardcore]
+            multiply_every = 50
+
+            [food_weights.zen]
+            golden = 0
+        "}).unwrap();
+
+        let defaults = Settings::default();
+        assert_eq!(settings.hazard_rules.classic, defaults.hazard_rules.classic);
+        assert_eq!(settings.hazard_rules.hardcore, HazardRules { multiply_every: 50, ..defaults.hazard_rules.hardcore });
+        assert_eq!(settings.food_weights.zen, FoodWeights { golden: 0, ..defaults.food_weights.zen });
+        assert_eq!(settings.food_weights.hardcore, defaults.food_weights.hardcore);
+    }
+
+    #[test]
+    fn unknown_rules_are_refused() {
+        assert!(toml::from_str::<Settings>("[hazard_rules.classic]\nspin = 1\n").is_err());
+        assert!(toml::from_str::<Settings>("[food_weights.zen]\nrotten_egg = 1\n").is_err());
+    }
+}
@@ -1,47 +1,35 @@
-use std::collections::LinkedList;
-use std::iter;
+use std::collections::VecDeque;
 
 use log::debug;
-use log::info;
 
-use crate::Border;
+use crate::Playfield;
 use crate::Direction;
 use crate::Position;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 #[allow(dead_code)]
 pub struct SnakeNode {
     position: Position,
-    direction: Option<Direction>,
-    following_direction: Option<Direction>, // Direction of the node behind: used to draw the corner segments
-}
-
-impl Default for SnakeNode {
-    fn default() -> Self {
-        SnakeNode { 
-            position: Position::default(),
-            direction: None,
-            following_direction: None,
-        }
-    }
+    portal: Option<Direction>, // Direction it was going when it came out of a portal on this cell
 }
 
 #[allow(dead_code)]
 impl SnakeNode {
     pub fn new(position: Position) -> Self {
-        SnakeNode { position, direction: None, following_direction: None }
+        SnakeNode { position, portal: None }
     }
 
-    pub fn get_position(&self) -> &Position {
-        &self.position
+    /// A node that came out of a portal on `position`, going in `direction`
+    pub fn through_portal(position: Position, direction: Direction) -> Self {
+        SnakeNode { position, portal: Some(direction) }
     }
 
-    pub fn get_direction(&self) -> Option<Direction> {
-        self.direction
+    pub fn get_portal(&self) -> Option<Direction> {
+        self.portal
     }
 
-    pub fn get_following_direction(&self) -> Option<Direction> {
-        self.following_direction
+    pub fn get_position(&self) -> &Position {
+        &self.position
     }
 
     pub fn get_position_mut(&mut self) -> &mut Position {
@@ -51,110 +39,84 @@ impl SnakeNode {
     pub fn set_position(&mut self, position: Position) {
         self.position = position;
     }
-
-    pub fn set_direction(&mut self, direction: Direction) {
-        self.direction = Some(direction);
-    }
-
-    pub fn set_following_direction(&mut self, direction: Direction) {
-        self.following_direction = Some(direction);
-    }
 }
 
+/// The snake, from the head (front) to the tail (back). Moving pushes a new head and pops the tail,
+/// so a move costs the same for any length; the other nodes never change.
 #[derive(Debug)]
 pub struct Snake {
     direction: Direction,
-    list: LinkedList<SnakeNode>,
-    boundaries: Option<Border>,
+    list: VecDeque<SnakeNode>,
+    boundaries: Option<Playfield>,
 }
 
 #[allow(dead_code)]
 impl Snake {
-    pub fn new(direction: Direction, mut head: SnakeNode, boundaries: Option<Border>) -> Self {
+    pub fn new(direction: Direction, mut head: SnakeNode, boundaries: Option<Playfield>) -> Self {
         if let Some(boundaries) = boundaries {
             head.get_position_mut().set_boundaries(boundaries);
         }
-        head.set_direction(direction);
 
         Snake {
             direction,
-            list: LinkedList::from([head]),
+            list: VecDeque::from([head]),
             boundaries,
         }
     }
 
-    pub fn set_boundaries(&mut self, boundaries: Border) {
+    pub fn set_boundaries(&mut self, boundaries: Playfield) {
         self.boundaries = Some(boundaries);
         for node in self.list.iter_mut() {
             node.get_position_mut().set_boundaries(boundaries);
         }
     }
 
-    // Checks if the head is blocked by another node and can't go in that direction
-    pub fn can_go_in_direction(&self, direction: Direction) -> bool {
-        let mut iterator = self.list.iter();
-        if let (Some(head), Some(node)) = (iterator.next(), iterator.next()) {
-            let head_position = head.get_position();
-            let node_position = node.get_position();
-            let same_line: bool = head_position.line == node_position.line;
-            let same_column: bool = head_position.column == node_position.column;
-
-            // Returns false if it can't go in that direction
-            match direction {
-                Direction::Up => return !(same_column && head_position.line - 1 == node_position.line),
-                Direction::Down => return !(same_column && head_position.line + 1 == node_position.line),
-                Direction::Left => return !(same_line && head_position.column - 1 == node_position.column),
-                Direction::Right => return !(same_line && head_position.column + 1 == node_position.column),
+    /// Moves every node to the same place inside `boundaries`, which must have the size of the current ones
+    pub fn move_to(&mut self, boundaries: Playfield) {
+        if let Some(current) = self.boundaries {
+            for node in self.list.iter_mut() {
+                let position = current.translate(node.get_position(), &boundaries);
+                node.set_position(position);
             }
         }
-        true
+        self.boundaries = Some(boundaries);
+    }
+
+    // Checks the snake isn't turning back on itself, the head alone can go anywhere
+    pub fn can_go_in_direction(&self, direction: Direction) -> bool {
+        self.list.len() == 1 || direction != self.direction.opposite()
+    }
+
+    /// Returns where the head would be after moving in `direction`
+    pub fn next_head_position(&self, direction: Direction) -> Position {
+        let mut position = *self.get_head().get_position();
+        position.move_towards(direction);
+        position
+    }
+
+    /// Returns true if moving in `direction` makes the head come out of the other side of the boundaries
+    pub fn next_head_wraps(&self, direction: Direction) -> bool {
+        let head_position = self.get_head().get_position();
+        let next_position = self.next_head_position(direction);
+        next_position.line.abs_diff(head_position.line) + next_position.column.abs_diff(head_position.column) != 1
     }
 
     pub fn get_positions(&self) -> Vec<Position> {
-        let mut positions: Vec<Position> = Vec::new();
-        for node in self.list.iter() {
-            positions.push(*node.get_position());
-        }
-        positions
-    }
-
-    pub fn update_positions(&mut self) {
-        info!("==== START UPDATING POSITION ====");
-        let direction = self.direction;
-        let mut iterator = self.list.iter_mut().peekable();
-        if let Some(head) = iterator.next() {
-            let mut previous_direction: Option<Direction> = Some(direction);
-            head.set_direction(direction);
-            let head_position = head.get_position_mut();
-            let mut previous_position: Position = *head_position;
-            match direction {
-                Direction::Up => head_position.decrement_line(1),
-                Direction::Down => head_position.increment_line(1),
-                Direction::Right => head_position.increment_col(1),
-                Direction::Left => head_position.decrement_col(1),
-            }
-            info!("Direction {:?}", direction);
-            info!("[HEAD] Before: {:?}, After: {:?}", previous_position, head_position);
-            while let Some(node) = iterator.next() {
-                let temp_position = *node.get_position();
-                let temp_direction = node.get_direction();
-                node.set_position(previous_position);
-
-                if let Some(direction) = previous_direction {
-                    node.set_direction(direction);
-                }
-
-                if let Some(following_node) = iterator.peek() {
-                    if let Some(direction) = following_node.get_direction() {
-                        node.set_following_direction(direction);
-                    }
-                }
-                info!("[NODE] Before: {:?}, After: {:?}", previous_position, temp_position);
-                previous_position = temp_position;
-                previous_direction = temp_direction;
-            }
-        }
-        info!("==== END UPDATING POSITION ====");
+        self.list.iter().map(|node| *node.get_position()).collect()
+    }
+
+    /// Moves the snake one cell in its direction and returns the cell the tail left. `exit` is where
+    /// the head comes out if that cell is a portal: the body is split between the two ends until
+    /// the tail goes through.
+    pub fn update_positions(&mut self, exit: Option<Position>) -> Position {
+        let head = match exit {
+            Some(exit) => SnakeNode::through_portal(exit, self.direction),
+            None => SnakeNode::new(self.next_head_position(self.direction)),
+        };
+        debug!("[Snake] Moved {:?} to {:?}", self.direction, head.get_position());
+        self.list.push_front(head);
+        let tail = self.list.pop_back().expect("Tail should be present");
+        *tail.get_position()
     }
 
     pub fn get_head(&self) -> &SnakeNode {
@@ -169,22 +131,38 @@ impl Snake {
         self.list.back_mut()
     }
 
-    pub fn get_list(&self) -> &LinkedList<SnakeNode> {
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn get_list(&self) -> &VecDeque<SnakeNode> {
         &self.list
     }
-    
-    pub fn is_eating_tail(&self) -> bool {
-        let mut iter = self.list.iter();
-        let head = iter.next();
-        if let Some(head) = head {
-            let position = head.get_position();
-            for node in iter {
-                if node.get_position().eq(position) {
-                    return true;
-                }
-            }
-        }
-        false
+
+    /// Returns the directions from the node at `index` towards the node before it (closer to the
+    /// head) and the node after it, None where there's no such node. Across a portal, it's the
+    /// direction of the portal end next to the node.
+    pub fn get_neighbour_directions(&self, index: usize) -> (Option<Direction>, Option<Direction>) {
+        let Some(node) = self.list.get(index) else {
+            return (None, None);
+        };
+        let position = node.get_position();
+        let towards = |other: Option<&SnakeNode>| other.and_then(|other| position.direction_to(other.get_position()));
+        let previous = index.checked_sub(1).and_then(|index| self.list.get(index));
+        let next = self.list.get(index + 1);
+        let towards_previous = match previous.and_then(SnakeNode::get_portal) {
+            Some(direction) => Some(direction), // Went into the portal from here
+            None => towards(previous),
+        };
+        let towards_next = match (node.portal, next) {
+            (Some(direction), Some(_)) => Some(direction.opposite()), // Came out of the portal here
+            _ => towards(next),
+        };
+        (towards_previous, towards_next)
     }
 
     pub fn add_tails(&mut self, count: u32) {
@@ -193,26 +171,29 @@ impl Snake {
         }
     }
 
+    /// Adds a node behind the tail, in line with the node before it (or behind the head)
     pub fn add_tail(&mut self) {
-        let current_tail = self.get_tail();
-        if let Some(current_tail) = current_tail {
-            let prev_position: Position = *current_tail.get_position();
-            let prev_direction: Direction = current_tail.get_direction().expect("Should have direction");
-            let mut position: Position = prev_position;
-            match prev_direction {
-                Direction::Up => position.increment_line(1),
-                Direction::Down => position.decrement_line(1),
-                Direction::Right => position.decrement_col(1),
-                Direction::Left => position.increment_col(1),
-            }
-            let mut new_tail = SnakeNode::new(position);
-            new_tail.set_direction(prev_direction);
-            if let Some(direction) = new_tail.get_direction() {
-                current_tail.set_following_direction(direction);
-            }
-            info!("[Added tail] Direction: {:?}, Before: {:?}, After: {:?}", self.direction, prev_position, position);
-            self.list.push_back(new_tail);
+        let backwards = match self.get_neighbour_directions(self.list.len() - 1) {
+            (Some(towards_previous), _) => towards_previous.opposite(),
+            (None, _) => self.direction.opposite(),
+        };
+        let mut position = *self.list.back().expect("Tail should be present").get_position();
+        position.move_towards(backwards);
+        self.add_tail_at(position);
+    }
+
+    /// Removes the tail and returns its cell, unless the head is the only node left
+    pub fn remove_tail(&mut self) -> Option<Position> {
+        if self.list.len() == 1 {
+            return None;
         }
+        self.list.pop_back().map(|node| *node.get_position())
+    }
+
+    /// Adds a node at `position`, which should be next to the tail (e.g. the cell it just left)
+    pub fn add_tail_at(&mut self, position: Position) {
+        debug!("[Snake] Added tail at {:?}", position);
+        self.list.push_back(SnakeNode::new(position));
     }
 
     pub fn get_direction(&self) -> Direction {
@@ -220,9 +201,7 @@ impl Snake {
     }
 
     pub fn change_direction(&mut self, direction: Direction) {
-        // self.prev_direction = self.direction;
         self.direction = direction;
-        self.get_head_mut().set_direction(direction);
     }
 }
 
@@ -231,3 +210,36 @@ impl Default for Snake {
         Self::new(Direction::Up, SnakeNode::default(), None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snake() -> Snake {
+        let mut snake = Snake::new(Direction::Right, SnakeNode::new(Position::new(5, 5)), None);
+        snake.add_tail_at(Position::new(5, 4));
+        snake.add_tail_at(Position::new(5, 3));
+        snake
+    }
+
+    #[test]
+    fn the_head_comes_out_of_the_other_portal() {
+        let mut snake = snake();
+        assert_eq!(snake.update_positions(Some(Position::new(2, 10))), Position::new(5, 3));
+        assert_eq!(snake.get_head().get_portal(), Some(Direction::Right));
+
+        snake.update_positions(None);
+        assert_eq!(snake.get_positions(), [Position::new(2, 11), Position::new(2, 10), Position::new(5, 5)]);
+    }
+
+    #[test]
+    fn the_body_points_at_the_portal_ends() {
+        let mut snake = snake();
+        snake.update_positions(Some(Position::new(2, 10)));
+        snake.update_positions(None);
+
+        // Came out of the exit going right, went into the entrance going right
+        assert_eq!(snake.get_neighbour_directions(1), (Some(Direction::Right), Some(Direction::Left)));
+        assert_eq!(snake.get_neighbour_directions(2), (Some(Direction::Right), None));
+    }
+}
@@ -0,0 +1,940 @@
+use std::time::Duration;
+
+use log::info;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::clock::SpeedCurve;
+use crate::food::{FoodKind, FoodWeights, EXPIRING_TICKS, ROTTEN_SHRINK, SPEED_EFFECT_TICKS};
+use crate::hazard::{self, HazardBehaviour, HazardRules, BLINK_OFF, BLINK_ON, DRIFT_EVERY, PATROL_EVERY};
+use crate::powerup::{EffectStack, PowerUp, MAGNET_RADIUS, MULTIPLIER, POWER_UP_EVERY, POWER_UP_LIFETIME, SLOW_MOTION_PERCENT};
+use crate::settings::{Settings, WallBehaviour, MAX_HAZARDS};
+use crate::occupancy::{Occupant, OccupancyGrid};
+use crate::snake::{Snake, SnakeNode};
+use crate::{Border, Direction, Playfield, Position};
+
+/// No hazard or portal spawns this close to the head, in both directions
+const SAFE_RADIUS: u16 = 2;
+/// Nor on the cells in front of the head
+const SAFE_AHEAD: u16 = 8;
+/// Share of the cells (hazards aside) the snake must always be able to reach, in percent
+const MIN_REACHABLE_PERCENT: usize = 90;
+/// Cells tried for a hazard before leaving it out
+const HAZARD_ATTEMPTS: u32 = 20;
+/// Cells tried for a portal end before leaving the pair out
+const PORTAL_ATTEMPTS: u32 = 20;
+/// Cells between the two ends of a portal, at least
+const MIN_PORTAL_DISTANCE: u16 = 4;
+/// Cells tried for the food before taking one the snake may not reach
+const FOOD_ATTEMPTS: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapItemType {
+    Food(FoodKind),
+    Hazard(HazardBehaviour),
+    PowerUp(PowerUp),
+    Portal(u8), // Pair it belongs to, entering one end takes the head out of the other
+}
+
+pub struct MapItem {
+    pub item_type: MapItemType,
+    pub position: Position,
+}
+
+impl MapItem {
+    pub fn new(item_type: MapItemType, position: Position) -> Self {
+        MapItem {
+            item_type,
+            position,
+        }
+    }
+
+    /// Returns true for the items the snake dies on
+    pub fn is_solid(&self) -> bool {
+        match self.item_type {
+            MapItemType::Food(_) | MapItemType::PowerUp(_) | MapItemType::Portal(_) => false,
+            MapItemType::Hazard(behaviour) => behaviour.is_solid(),
+        }
+    }
+}
+
+impl Default for MapItem {
+    fn default() -> Self {
+        Self::new(MapItemType::Food(FoodKind::Regular), Position::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    Tail,
+    Hazard,
+    Wall,
+}
+
+/// What happened during a single call to [`GameState::step`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickOutcome {
+    Moved,
+    AteFood,
+    Died(DeathCause),
+    Won, // The snake fills the whole board
+}
+
+impl TickOutcome {
+    /// Returns true when the game can't go on
+    pub fn is_over(self) -> bool {
+        matches!(self, TickOutcome::Died(_) | TickOutcome::Won)
+    }
+}
+
+/// Rules of the game without any terminal I/O: the terminal driver (or a bot, or a test)
+/// feeds it inputs and reads back the state to render it.
+pub struct GameState {
+    score: u16,
+    food: MapItem,
+    hazards: Vec<MapItem>,
+    portals: Vec<MapItem>, // Both ends of every pair, next to each other
+    snake: Snake,
+    border: Border,
+    playfield: Playfield,
+    occupancy: OccupancyGrid,
+    walls: WallBehaviour,
+    hazard_rules: HazardRules, // Of the mode played
+    food_weights: FoodWeights, // Of the mode played
+    food_expires: Option<u64>, // Tick an expiring fruit goes away at
+    speed_effect: Option<(u32, u64)>, // Tick duration in percent of the normal one, until that tick
+    power_up: Option<MapItem>,
+    next_power_up: u64, // Tick the next power-up shows up at, or the one on the board goes away at
+    effects: EffectStack,
+    overlaps: usize, // Cells the snake went through without dying, still under its body
+    speed_curve: SpeedCurve,
+    tick_rate: u64, // Milliseconds between two ticks at the start
+    seed: u64,
+    rng: StdRng, // Every random choice of the game comes from it, so the seed is enough to replay a game
+    tick: u64,
+    food_eaten: u32,
+    max_length: usize,
+    death_cause: Option<DeathCause>,
+    won: bool,
+}
+
+impl GameState {
+    /// Creates a new game, placing everything randomly from `seed` (or a random one if `None`)
+    pub fn new(border: Border, settings: &Settings, seed: Option<u64>) -> Self {
+        info!("[Border]\n{:#?}", border);
+        let seed = seed.unwrap_or_else(rand::random);
+        let mut rng = StdRng::seed_from_u64(seed);
+        info!("[Seed] {seed}");
+        let playfield = Playfield::new(&border);
+        // The body starts under the head (going up) in a straight line, so a snake longer than the
+        // playfield is high starts shorter instead of wrapping onto itself
+        let body_length = settings.start_length.saturating_sub(1).min(playfield.get_height().saturating_sub(1));
+        let spawn_area = Playfield {
+            last_line: playfield.last_line.saturating_sub(body_length).max(playfield.first_line),
+            ..playfield
+        };
+        let mut head_position = spawn_area.random_position(&mut rng);
+        head_position.set_boundaries(playfield);
+        let mut snake = Snake::new(
+            Direction::Up,
+            SnakeNode::new(
+                head_position
+            ),
+            Some(playfield)
+        );
+        snake.add_tails(u32::from(body_length));
+        let mut occupancy = OccupancyGrid::new(playfield);
+        for position in snake.get_positions() {
+            occupancy.set(&position, Occupant::Snake);
+        }
+
+        let food_position = occupancy.random_free(&mut rng).unwrap_or_default();
+        occupancy.set(&food_position, Occupant::Food);
+        let food_weights = settings.food_weights.get(settings.mode);
+        let food_kind = food_weights.pick(&mut rng);
+        let food_expires = (food_kind == FoodKind::Expiring).then_some(EXPIRING_TICKS);
+        let food = MapItem::new(MapItemType::Food(food_kind), food_position);
+
+        let portals = Self::place_portals(settings.portal_pairs, &snake, &mut occupancy, &mut rng);
+
+        let wrap = settings.walls == WallBehaviour::Wrap;
+        let hazard_rules = settings.hazard_rules.get(settings.mode);
+        let mut hazards = Self::place_hazards(settings.hazards_count, &snake, &food_position, wrap, &mut occupancy, &mut rng);
+        Self::assign_behaviours(&mut hazards, &hazard_rules, &mut rng);
+
+        let max_length = snake.len();
+        GameState {
+            score: 0,
+            food,
+            hazards,
+            portals,
+            snake,
+            border,
+            playfield,
+            occupancy,
+            walls: settings.walls,
+            hazard_rules,
+            food_weights,
+            food_expires,
+            speed_effect: None,
+            power_up: None,
+            next_power_up: POWER_UP_EVERY,
+            effects: EffectStack::default(),
+            overlaps: 0,
+            speed_curve: settings.speed_curve,
+            tick_rate: settings.tick_rate,
+            seed,
+            rng,
+            tick: 0,
+            food_eaten: 0,
+            max_length,
+            death_cause: None,
+            won: false,
+        }
+    }
+
+    /// Marks `position` as a hazard if it's a free cell away from the head and its heading, and the
+    /// snake can still reach the food and most of the board. Returns false, changing nothing, if not.
+    fn claim_hazard_cell(occupancy: &mut OccupancyGrid, position: &Position, snake: &Snake, food: &Position, wrap: bool, hazard_count: usize) -> bool {
+        let head = *snake.get_head().get_position();
+        if !occupancy.is_free(position) || Self::in_safe_zone(snake, position) {
+            return false;
+        }
+
+        occupancy.set(position, Occupant::Hazard);
+        if Self::leaves_room(occupancy, &head, food, wrap, hazard_count) {
+            return true;
+        }
+        occupancy.set(position, Occupant::Empty);
+        false
+    }
+
+    /// Returns true if the snake can reach the food and most of the board, with `hazard_count`
+    /// hazards on it besides the one being placed or moved
+    fn leaves_room(occupancy: &OccupancyGrid, head: &Position, food: &Position, wrap: bool, hazard_count: usize) -> bool {
+        let reach = occupancy.flood_fill(head, wrap);
+        let open_cells = occupancy.cell_count() - hazard_count - 1;
+        reach.contains(food) && reach.count() * 100 >= open_cells * MIN_REACHABLE_PERCENT
+    }
+
+    /// Moves a hazard like [`hazard::try_move`], unless it would wall the snake in.
+    /// Returns false if it stayed.
+    fn move_hazard(occupancy: &mut OccupancyGrid, position: &mut Position, direction: Direction, head: &Position, food: &Position, wrap: bool, hazard_count: usize) -> bool {
+        let from = *position;
+        if !hazard::try_move(occupancy, position, direction, head, wrap) {
+            return false;
+        }
+        if Self::leaves_room(occupancy, head, food, wrap, hazard_count - 1) {
+            return true;
+        }
+        occupancy.set(position, Occupant::Empty);
+        occupancy.set(&from, Occupant::Hazard);
+        *position = from;
+        false
+    }
+
+    /// Returns true if `position` is close to the head or in front of it
+    fn in_safe_zone(snake: &Snake, position: &Position) -> bool {
+        let head = *snake.get_head().get_position();
+        let mut ahead = head;
+        let in_front = (0..SAFE_AHEAD).any(|_| {
+            ahead.move_towards(snake.get_direction());
+            ahead == *position
+        });
+        in_front || (position.line.abs_diff(head.line) <= SAFE_RADIUS && position.column.abs_diff(head.column) <= SAFE_RADIUS)
+    }
+
+    /// Places up to `pairs` portals, both ends on free cells away from the head and from each other
+    fn place_portals(pairs: u8, snake: &Snake, occupancy: &mut OccupancyGrid, rng: &mut StdRng) -> Vec<MapItem> {
+        let mut portals: Vec<MapItem> = Vec::new();
+        let random_end = |occupancy: &OccupancyGrid, rng: &mut StdRng, other: Option<&Position>| {
+            (0..PORTAL_ATTEMPTS).find_map(|_| {
+                let position = occupancy.random_free(rng)?;
+                let far_enough = other.is_none_or(|other| position.line.abs_diff(other.line) + position.column.abs_diff(other.column) >= MIN_PORTAL_DISTANCE);
+                (far_enough && !Self::in_safe_zone(snake, &position)).then_some(position)
+            })
+        };
+        for pair in 0..pairs {
+            let Some(first) = random_end(occupancy, rng, None) else {
+                break;
+            };
+            let Some(second) = random_end(occupancy, rng, Some(&first)) else {
+                break;
+            };
+            info!("[Portals] Pair {pair} between {:?} and {:?}", (first.line, first.column), (second.line, second.column));
+            for position in [first, second] {
+                occupancy.set(&position, Occupant::Portal);
+                portals.push(MapItem::new(MapItemType::Portal(pair), position));
+            }
+        }
+        portals
+    }
+
+    /// Returns where the head comes out when entering `position`, None if it isn't a portal
+    fn portal_exit(&self, position: &Position) -> Option<Position> {
+        let index = self.portals.iter().position(|portal| portal.position == *position)?;
+        // The ends of a pair are next to each other in the list
+        Some(self.portals[index ^ 1].position)
+    }
+
+    /// Places up to `count` still hazards on free cells, following [`GameState::claim_hazard_cell`]
+    fn place_hazards(count: u8, snake: &Snake, food: &Position, wrap: bool, occupancy: &mut OccupancyGrid, rng: &mut StdRng) -> Vec<MapItem> {
+        let mut hazards: Vec<MapItem> = Vec::new();
+        for _ in 0..count {
+            let placed = (0..HAZARD_ATTEMPTS).find_map(|_| {
+                let position = occupancy.random_free(rng)?;
+                Self::claim_hazard_cell(occupancy, &position, snake, food, wrap, hazards.len()).then_some(position)
+            });
+            let Some(position) = placed else {
+                info!("[Hazards] No room left after {} hazards", hazards.len());
+                break;
+            };
+            hazards.push(MapItem::new(MapItemType::Hazard(HazardBehaviour::Static), position));
+        }
+        hazards
+    }
+
+    /// Makes the first hazards patrol, then drift, then blink, as many of each as the rules say
+    fn assign_behaviours(hazards: &mut [MapItem], rules: &HazardRules, rng: &mut StdRng) {
+        let patrol = usize::from(rules.patrol);
+        let drift = patrol + usize::from(rules.drift);
+        let blink = drift + usize::from(rules.blink);
+        for (index, hazard) in hazards.iter_mut().enumerate() {
+            let behaviour = if index < patrol {
+                HazardBehaviour::Patrol(Direction::ALL[rng.random_range(0..Direction::ALL.len())])
+            } else if index < drift {
+                HazardBehaviour::Drift
+            } else if index < blink {
+                // Out of step with each other, all there at the start
+                HazardBehaviour::Blink(rng.random_range(0..BLINK_ON))
+            } else {
+                HazardBehaviour::Static
+            };
+            hazard.item_type = MapItemType::Hazard(behaviour);
+        }
+    }
+
+    /// Picks a free cell for the food, one the snake can reach and no blinking hazard comes back
+    /// to if it finds one. None if the board is full.
+    fn random_food_position(&mut self) -> Option<Position> {
+        let reach = self.occupancy.flood_fill(self.snake.get_head().get_position(), self.walls == WallBehaviour::Wrap);
+        let hazards = &self.hazards;
+        let fits = |position: &Position| reach.contains(position) && !hazards.iter().any(|hazard| hazard.position == *position);
+        let reachable = (0..FOOD_ATTEMPTS).find_map(|_| self.occupancy.random_free(&mut self.rng).filter(fits));
+        reachable.or_else(|| self.occupancy.random_free(&mut self.rng))
+    }
+
+    /// Moves, blinks and multiplies the hazards following the rules of the mode. They never go on
+    /// the snake, the food or right next to the head.
+    fn update_hazards(&mut self) {
+        let head = *self.snake.get_head().get_position();
+        let wrap = self.walls == WallBehaviour::Wrap;
+        let tick = self.tick;
+        let food = self.food.position;
+        let hazard_count = self.hazards.len();
+        for hazard in self.hazards.iter_mut() {
+            let MapItemType::Hazard(behaviour) = hazard.item_type else {
+                continue;
+            };
+            // Under the body of a ghost snake: stays put until it's gone
+            if hazard.is_solid() && self.occupancy.get(&hazard.position) != Occupant::Hazard {
+                continue;
+            }
+            let behaviour = match behaviour {
+                HazardBehaviour::Patrol(direction) if tick.is_multiple_of(PATROL_EVERY) => {
+                    if Self::move_hazard(&mut self.occupancy, &mut hazard.position, direction, &head, &food, wrap, hazard_count) {
+                        behaviour
+                    } else {
+                        HazardBehaviour::Patrol(direction.opposite()) // Goes back the next time
+                    }
+                }
+                HazardBehaviour::Drift if tick.is_multiple_of(DRIFT_EVERY) => {
+                    for direction in hazard::directions_toward(&hazard.position, &head) {
+                        if Self::move_hazard(&mut self.occupancy, &mut hazard.position, direction, &head, &food, wrap, hazard_count) {
+                            break;
+                        }
+                    }
+                    behaviour
+                }
+                HazardBehaviour::Blink(phase) if phase + 1 == BLINK_ON => {
+                    self.occupancy.set(&hazard.position, Occupant::Empty);
+                    HazardBehaviour::Blink(phase + 1)
+                }
+                HazardBehaviour::Blink(phase) if phase + 1 == BLINK_ON + BLINK_OFF => {
+                    // Comes back on a free cell only, without walling the snake in, keeps warning until then
+                    if !hazard::can_enter(&self.occupancy, &hazard.position, &head) {
+                        behaviour
+                    } else {
+                        self.occupancy.set(&hazard.position, Occupant::Hazard);
+                        if Self::leaves_room(&self.occupancy, &head, &food, wrap, hazard_count - 1) {
+                            HazardBehaviour::Blink(0)
+                        } else {
+                            self.occupancy.set(&hazard.position, Occupant::Empty);
+                            behaviour
+                        }
+                    }
+                }
+                HazardBehaviour::Blink(phase) => HazardBehaviour::Blink(phase + 1),
+                behaviour => behaviour,
+            };
+            hazard.item_type = MapItemType::Hazard(behaviour);
+        }
+
+        let every = self.hazard_rules.multiply_every;
+        if every > 0 && tick.is_multiple_of(every) {
+            self.multiply_hazard();
+        }
+    }
+
+    /// Adds a hazard next to a random one, behaving like it
+    fn multiply_hazard(&mut self) {
+        let parents: Vec<&MapItem> = self.hazards.iter().filter(|hazard| hazard.is_solid()).collect();
+        if parents.is_empty() || self.hazards.len() >= usize::from(MAX_HAZARDS) {
+            return;
+        }
+        let parent = parents[self.rng.random_range(0..parents.len())];
+        let (position, item_type) = (parent.position, parent.item_type);
+
+        let wrap = self.walls == WallBehaviour::Wrap;
+        let mut directions = Direction::ALL;
+        directions.shuffle(&mut self.rng);
+        for direction in directions {
+            let mut next = position;
+            next.move_towards(direction);
+            let wrapped = next.line.abs_diff(position.line) + next.column.abs_diff(position.column) != 1;
+            if (wrapped && !wrap)
+                || !Self::claim_hazard_cell(&mut self.occupancy, &next, &self.snake, &self.food.position, wrap, self.hazards.len()) {
+                continue;
+            }
+            info!("[Hazards] Multiplied at line {} and column {}", next.line, next.column);
+            self.hazards.push(MapItem::new(item_type, next));
+            return;
+        }
+    }
+
+    /// Advances the game by one tick, turning the snake first if `input` is a legal direction
+    pub fn step(&mut self, input: Option<Direction>) -> TickOutcome {
+        let outcome = self.advance(input);
+        if !outcome.is_over() {
+            self.update_hazards();
+            self.update_food();
+            self.update_power_ups();
+        }
+        match outcome {
+            TickOutcome::Died(cause) => self.death_cause = Some(cause),
+            TickOutcome::Won => self.won = true,
+            _ => (),
+        }
+        outcome
+    }
+
+    fn advance(&mut self, input: Option<Direction>) -> TickOutcome {
+        self.tick += 1;
+        if let Some(direction) = input && self.snake.can_go_in_direction(direction) {
+            self.snake.change_direction(direction);
+        }
+
+        let direction = self.snake.get_direction();
+        if self.snake.next_head_wraps(direction) {
+            match self.walls {
+                WallBehaviour::Wrap => (),
+                WallBehaviour::Solid if !self.effects.consume(PowerUp::Shield) => return TickOutcome::Died(DeathCause::Wall),
+                WallBehaviour::Solid | WallBehaviour::Bounce => {
+                    // With a shield the snake bounces off solid walls too
+                    // Turns along the wall, to the right if it can
+                    let turn = [direction.clockwise(), direction.clockwise().opposite()]
+                        .into_iter()
+                        .find(|turn| !self.snake.next_head_wraps(*turn));
+                    let Some(turn) = turn else {
+                        return TickOutcome::Died(DeathCause::Wall);
+                    };
+                    self.snake.change_direction(turn);
+                }
+            }
+        }
+
+        // The tail leaves its cell before the head enters the next one
+        let exit = self.portal_exit(&self.snake.next_head_position(self.snake.get_direction()));
+        let tail_position = self.snake.update_positions(exit);
+        let left = self.occupant_left_at(&tail_position);
+        self.occupancy.set(&tail_position, left);
+
+        let head_position = *self
+            .snake
+            .get_head()
+            .get_position();
+        let occupant = self.occupancy.get(&head_position);
+        self.occupancy.set(&head_position, Occupant::Snake);
+        match occupant {
+            Occupant::Snake | Occupant::Hazard => {
+                if !self.effects.is_active(PowerUp::Ghost) && !self.effects.consume(PowerUp::Shield) {
+                    return TickOutcome::Died(if occupant == Occupant::Snake { DeathCause::Tail } else { DeathCause::Hazard });
+                }
+                // Goes through, the cell is given back once the tail leaves it
+                self.overlaps += 1;
+                return TickOutcome::Moved;
+            }
+            Occupant::PowerUp => {
+                self.pick_power_up();
+                return TickOutcome::Moved;
+            }
+            Occupant::Food => (),
+            Occupant::Empty | Occupant::Portal => return TickOutcome::Moved,
+        }
+
+        let kind = self.get_food_kind();
+        info!(
+            "Ate {} food at line {} and column {}",
+            kind.name(), self.food.position.line, self.food.position.column
+        );
+        let multiplier = if self.effects.is_active(PowerUp::Multiplier) { MULTIPLIER } else { 1 };
+        self.score = self.score.saturating_add(kind.points() * multiplier);
+        self.food_eaten += 1;
+        if kind.grows() {
+            // Grows on the cell the tail just left, which is the only one sure to be free
+            self.snake.add_tail_at(tail_position);
+            self.occupancy.set(&tail_position, Occupant::Snake);
+            if matches!(left, Occupant::Snake | Occupant::Hazard) {
+                self.overlaps += 1; // Still over what was left there
+            }
+            self.max_length = self.max_length.max(self.snake.len());
+        } else {
+            for _ in 0..ROTTEN_SHRINK {
+                if let Some(position) = self.snake.remove_tail() {
+                    let left = self.occupant_left_at(&position);
+                    self.occupancy.set(&position, left);
+                }
+            }
+        }
+        if let Some(percent) = kind.tick_percent() {
+            self.speed_effect = Some((percent, self.tick + SPEED_EFFECT_TICKS));
+        }
+
+        // No room left for the food: the snake fills the board
+        if !self.respawn_food() {
+            info!("Board full");
+            return TickOutcome::Won;
+        }
+        TickOutcome::AteFood
+    }
+
+    /// Puts a new food of a random kind on a free cell, returns false if there's none
+    fn respawn_food(&mut self) -> bool {
+        let Some(food_position) = self.random_food_position() else {
+            return false;
+        };
+        let kind = self.food_weights.pick(&mut self.rng);
+        self.food = MapItem::new(MapItemType::Food(kind), food_position);
+        self.food_expires = (kind == FoodKind::Expiring).then_some(self.tick + EXPIRING_TICKS);
+        self.occupancy.set(&food_position, Occupant::Food);
+        true
+    }
+
+    /// Moves an expiring fruit somewhere else once its countdown is over, and ends the speed effects
+    fn update_food(&mut self) {
+        if let Some((_, until)) = self.speed_effect && self.tick >= until {
+            self.speed_effect = None;
+        }
+        if let Some(expires) = self.food_expires && self.tick >= expires {
+            info!("[Food] Expired at line {} and column {}", self.food.position.line, self.food.position.column);
+            self.occupancy.set(&self.food.position, Occupant::Empty);
+            if !self.respawn_food() {
+                // Can't happen: the cell it left is free
+                self.occupancy.set(&self.food.position, Occupant::Food);
+                self.food_expires = None;
+            }
+        }
+    }
+
+    /// Returns what's left on a cell the snake just left: usually nothing, but it may have gone
+    /// through itself or a hazard there, or come out of a portal
+    fn occupant_left_at(&mut self, position: &Position) -> Occupant {
+        if self.overlaps > 0 {
+            if self.snake.get_list().iter().any(|node| node.get_position() == position) {
+                self.overlaps -= 1;
+                return Occupant::Snake;
+            }
+            if self.hazards.iter().any(|hazard| hazard.position == *position && hazard.is_solid()) {
+                self.overlaps -= 1;
+                return Occupant::Hazard;
+            }
+        }
+        if self.portals.iter().any(|portal| portal.position == *position) {
+            return Occupant::Portal;
+        }
+        Occupant::Empty
+    }
+
+    /// Starts the effect of the power-up under the head
+    fn pick_power_up(&mut self) {
+        let Some(MapItem { item_type: MapItemType::PowerUp(power_up), .. }) = self.power_up.take() else {
+            return;
+        };
+        info!("[PowerUp] Picked {}", power_up.name());
+        self.effects.push(power_up, self.tick);
+        self.next_power_up = self.tick + POWER_UP_EVERY;
+    }
+
+    /// Ends the effects whose time is over, makes the power-ups come and go, and pulls the food in
+    /// during the magnet
+    fn update_power_ups(&mut self) {
+        self.effects.expire(self.tick);
+        if self.tick >= self.next_power_up {
+            match self.power_up.take() {
+                Some(power_up) => {
+                    info!("[PowerUp] Went away at line {} and column {}", power_up.position.line, power_up.position.column);
+                    self.occupancy.set(&power_up.position, Occupant::Empty);
+                    self.next_power_up = self.tick + POWER_UP_EVERY;
+                }
+                None => {
+                    if let Some(position) = self.random_food_position() {
+                        let power_up = PowerUp::ALL[self.rng.random_range(0..PowerUp::ALL.len())];
+                        info!("[PowerUp] {} at line {} and column {}", power_up.name(), position.line, position.column);
+                        self.occupancy.set(&position, Occupant::PowerUp);
+                        self.power_up = Some(MapItem::new(MapItemType::PowerUp(power_up), position));
+                    }
+                    self.next_power_up = self.tick + POWER_UP_LIFETIME;
+                }
+            }
+        }
+
+        if self.effects.is_active(PowerUp::Magnet) {
+            let head = *self.snake.get_head().get_position();
+            let food = self.food.position;
+            if food.line.abs_diff(head.line) <= MAGNET_RADIUS && food.column.abs_diff(head.column) <= MAGNET_RADIUS {
+                for direction in hazard::directions_toward(&food, &head) {
+                    let mut next = food;
+                    next.move_towards(direction);
+                    if self.occupancy.is_free(&next) {
+                        self.occupancy.set(&food, Occupant::Empty);
+                        self.occupancy.set(&next, Occupant::Food);
+                        self.food.position = next;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Time between two ticks following the speed curve at the current score, changed by the speed
+    /// and slow fruits and the slow motion while their effect lasts, never under the curve minimum
+    pub fn tick_duration(&self) -> Duration {
+        let mut duration = self.speed_curve.tick_duration(self.tick_rate, self.score);
+        if let Some((percent, _)) = self.speed_effect {
+            duration = duration * percent / 100;
+        }
+        if self.effects.is_active(PowerUp::SlowMotion) {
+            duration = duration * SLOW_MOTION_PERCENT / 100;
+        }
+        duration.max(self.speed_curve.min_tick_duration())
+    }
+
+    /// Changes the speed the game goes on at, e.g. after changing the settings during a pause
+    pub fn set_speed(&mut self, curve: SpeedCurve, tick_rate: u64) {
+        self.speed_curve = curve;
+        self.tick_rate = tick_rate;
+    }
+
+    /// Seconds `ticks` ticks last at the current speed, rounded up
+    pub fn seconds_for(&self, ticks: u64) -> u64 {
+        let millis = u64::try_from(self.tick_duration().as_millis()).unwrap_or(u64::MAX);
+        millis.saturating_mul(ticks).div_ceil(1000)
+    }
+
+    /// Moves the arena to `border`, which must be the same size (e.g. to keep it centered when the
+    /// terminal is resized): everything stays at the same place inside it
+    pub fn move_to(&mut self, border: Border) {
+        let playfield = Playfield::new(&border);
+        info!("[Border] Moved to {:?}", border);
+        self.food.position = self.playfield.translate(&self.food.position, &playfield);
+        for hazard in self.hazards.iter_mut() {
+            hazard.position = self.playfield.translate(&hazard.position, &playfield);
+        }
+        if let Some(power_up) = self.power_up.as_mut() {
+            power_up.position = self.playfield.translate(&power_up.position, &playfield);
+        }
+        for portal in self.portals.iter_mut() {
+            portal.position = self.playfield.translate(&portal.position, &playfield);
+        }
+        self.snake.move_to(playfield);
+        self.occupancy.move_to(playfield);
+        self.border = border;
+        self.playfield = playfield;
+    }
+
+    pub fn get_score(&self) -> u16 {
+        self.score
+    }
+
+    pub fn get_food(&self) -> &MapItem {
+        &self.food
+    }
+
+    pub fn get_food_kind(&self) -> FoodKind {
+        match self.food.item_type {
+            MapItemType::Food(kind) => kind,
+            MapItemType::Hazard(_) | MapItemType::PowerUp(_) | MapItemType::Portal(_) => FoodKind::Regular,
+        }
+    }
+
+    /// Ticks left before the expiring fruit goes away, None for the other kinds
+    pub fn get_food_countdown(&self) -> Option<u64> {
+        self.food_expires.map(|expires| expires.saturating_sub(self.tick))
+    }
+
+    pub fn get_portals(&self) -> &[MapItem] {
+        &self.portals
+    }
+
+    pub fn get_power_up(&self) -> Option<&MapItem> {
+        self.power_up.as_ref()
+    }
+
+    pub fn get_effects(&self) -> &EffectStack {
+        &self.effects
+    }
+
+    pub fn get_hazards(&self) -> &[MapItem] {
+        &self.hazards
+    }
+
+    pub fn get_snake(&self) -> &Snake {
+        &self.snake
+    }
+
+    pub fn get_border(&self) -> Border {
+        self.border
+    }
+
+    pub fn get_playfield(&self) -> Playfield {
+        self.playfield
+    }
+
+    pub fn get_occupancy(&self) -> &OccupancyGrid {
+        &self.occupancy
+    }
+
+    pub fn get_walls(&self) -> WallBehaviour {
+        self.walls
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn get_food_eaten(&self) -> u32 {
+        self.food_eaten
+    }
+
+    pub fn get_max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Returns true once the snake fills the whole board
+    pub fn is_won(&self) -> bool {
+        self.won
+    }
+
+    /// Returns what killed the snake, None while it's alive
+    pub fn get_death_cause(&self) -> Option<DeathCause> {
+        self.death_cause
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::FoodWeights;
+
+    fn layout(state: &GameState) -> Vec<Position> {
+        let mut positions = state.get_snake().get_positions();
+        positions.push(state.get_food().position);
+        positions.extend(state.get_hazards().iter().map(|hazard| hazard.position));
+        positions
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let border = Border::new(1, 40, 2, 20);
+        let settings = Settings::default();
+        let mut first = GameState::new(border, &settings, Some(42));
+        let mut second = GameState::new(border, &settings, Some(42));
+        assert_eq!(first.get_seed(), 42);
+        assert_eq!(layout(&first), layout(&second));
+
+        for _ in 0..50 {
+            assert_eq!(first.step(None), second.step(None));
+            assert_eq!(layout(&first), layout(&second));
+        }
+        assert_ne!(layout(&GameState::new(border, &settings, Some(43))), layout(&first));
+    }
+
+    #[test]
+    fn hazards_stay_clear_of_the_head_and_the_way_to_the_food() {
+        let playfield = Playfield::new(&Border::new(1, 22, 1, 12)); // Lines 2..=11, columns 2..=21
+        let mut occupancy = OccupancyGrid::new(playfield);
+        let mut snake = Snake::new(Direction::Up, SnakeNode::new(Position::new(9, 3)), Some(playfield));
+        snake.add_tails(2);
+        let food = Position::new(11, 21);
+        occupancy.set(&food, Occupant::Food);
+        occupancy.set(&Position::new(10, 21), Occupant::Hazard);
+
+        for (position, reason) in [
+            (Position::new(10, 4), "next to the head"),
+            (Position::new(5, 3), "in front of the head"),
+            (food, "on the food"),
+            (Position::new(11, 20), "walling off the food"),
+        ] {
+            assert!(!GameState::claim_hazard_cell(&mut occupancy, &position, &snake, &food, false, 1), "{reason}");
+            assert_ne!(occupancy.get(&position), Occupant::Hazard, "{reason}");
+        }
+        assert!(GameState::claim_hazard_cell(&mut occupancy, &Position::new(5, 10), &snake, &food, false, 1));
+        assert_eq!(occupancy.get(&Position::new(5, 10)), Occupant::Hazard);
+    }
+
+    /// A 20 x 10 playfield
+    fn border() -> Border {
+        Border::new(1, 22, 1, 12)
+    }
+
+    /// Settings with nothing but the snake and regular food
+    fn settings(start_length: u16, walls: WallBehaviour) -> Settings {
+        let mut settings = Settings { hazards_count: 0, portal_pairs: 0, start_length, walls, ..Settings::default() };
+        settings.food_weights.classic = FoodWeights { regular: 1, golden: 0, rotten: 0, speed: 0, slow: 0, expiring: 0 };
+        settings
+    }
+
+    fn place_food(state: &mut GameState, position: Position) {
+        state.occupancy.set(&state.food.position, Occupant::Empty);
+        state.food.position = position;
+        state.occupancy.set(&position, Occupant::Food);
+    }
+
+    /// Puts the food two cells to the left of the head, out of the way of the tests
+    fn move_food_aside(state: &mut GameState) {
+        let mut aside = *state.get_snake().get_head().get_position();
+        aside.move_towards(Direction::Left);
+        aside.move_towards(Direction::Left);
+        place_food(state, aside);
+    }
+
+    #[test]
+    fn hazards_dont_move_where_they_wall_off_the_food() {
+        let playfield = Playfield::new(&Border::new(1, 22, 1, 12)); // Lines 2..=11, columns 2..=21
+        let mut occupancy = OccupancyGrid::new(playfield);
+        let head = Position::new(3, 3);
+        let food = Position::new(11, 21);
+        occupancy.set(&food, Occupant::Food);
+        occupancy.set(&Position::new(10, 21), Occupant::Hazard);
+        let mut blocking = Position::new(10, 20);
+        let mut free = Position::new(5, 10);
+        occupancy.set(&blocking, Occupant::Hazard);
+        occupancy.set(&free, Occupant::Hazard);
+
+        assert!(!GameState::move_hazard(&mut occupancy, &mut blocking, Direction::Down, &head, &food, false, 3));
+        assert_eq!(blocking, Position::new(10, 20));
+        assert_eq!(occupancy.get(&blocking), Occupant::Hazard);
+        assert!(occupancy.is_free(&Position::new(11, 20)));
+
+        assert!(GameState::move_hazard(&mut occupancy, &mut free, Direction::Right, &head, &food, false, 3));
+        assert_eq!(free, Position::new(5, 11));
+        assert!(occupancy.is_free(&Position::new(5, 10)));
+        assert!(GameState::leaves_room(&occupancy, &head, &food, false, 3));
+    }
+
+    #[test]
+    fn a_snake_longer_than_the_playfield_is_high_starts_shorter() {
+        let border = Border::new(1, 22, 1, 5); // Lines 2..=4
+        let settings = Settings { start_length: 4, ..Settings::default() };
+        for seed in 0..20 {
+            let state = GameState::new(border, &settings, Some(seed));
+            let positions = state.get_snake().get_positions();
+            assert_eq!(positions.len(), 3);
+            for position in &positions {
+                assert_eq!(state.occupancy.get(position), Occupant::Snake);
+                assert_eq!(positions.iter().filter(|other| Position::equals(other, position)).count(), 1);
+            }
+            for item in layout(&state).iter().skip(positions.len()) {
+                assert!(!positions.contains(item));
+            }
+        }
+    }
+
+    #[test]
+    fn moving_goes_one_cell_ahead_and_frees_the_tail() {
+        let mut state = GameState::new(border(), &settings(3, WallBehaviour::Wrap), Some(1));
+        move_food_aside(&mut state);
+        let ahead = state.get_snake().next_head_position(Direction::Up);
+        let tail = *state.get_snake().get_list().back().unwrap().get_position();
+
+        assert_eq!(state.step(None), TickOutcome::Moved);
+        assert_eq!(*state.get_snake().get_head().get_position(), ahead);
+        assert_eq!(state.get_snake().len(), 3);
+        assert_eq!(state.get_occupancy().get(&tail), Occupant::Empty);
+        assert_eq!(state.get_tick(), 1);
+    }
+
+    #[test]
+    fn eating_food_grows_the_snake_and_moves_the_food() {
+        let mut state = GameState::new(border(), &settings(3, WallBehaviour::Wrap), Some(2));
+        let ahead = state.get_snake().next_head_position(Direction::Up);
+        place_food(&mut state, ahead);
+
+        assert_eq!(state.step(None), TickOutcome::AteFood);
+        assert_eq!(state.get_snake().len(), 4);
+        assert_eq!(state.get_score(), 1);
+        assert_eq!(state.get_food_eaten(), 1);
+        assert_ne!(state.get_food().position, ahead);
+        assert_eq!(state.get_occupancy().get(&state.get_food().position), Occupant::Food);
+    }
+
+    #[test]
+    fn running_into_the_body_kills_the_snake() {
+        let mut state = GameState::new(border(), &settings(5, WallBehaviour::Wrap), Some(3));
+        move_food_aside(&mut state);
+
+        assert_eq!(state.step(Some(Direction::Right)), TickOutcome::Moved);
+        assert_eq!(state.step(Some(Direction::Down)), TickOutcome::Moved);
+        assert_eq!(state.step(Some(Direction::Left)), TickOutcome::Died(DeathCause::Tail));
+        assert_eq!(state.get_death_cause(), Some(DeathCause::Tail));
+    }
+
+    #[test]
+    fn solid_walls_kill_the_snake() {
+        let mut state = GameState::new(border(), &settings(1, WallBehaviour::Solid), Some(4));
+        move_food_aside(&mut state);
+
+        let outcome = (0..=state.get_playfield().get_height())
+            .map(|_| state.step(None))
+            .find(|outcome| outcome.is_over());
+        assert_eq!(outcome, Some(TickOutcome::Died(DeathCause::Wall)));
+        assert_eq!(state.get_snake().get_head().get_position().line, state.get_playfield().first_line);
+    }
+
+    #[test]
+    fn filling_the_board_wins() {
+        // One column of three cells: the snake takes two, the food the last one
+        let mut state = GameState::new(Border::new(1, 3, 1, 5), &settings(2, WallBehaviour::Wrap), Some(5));
+        assert_eq!(state.get_occupancy().free_count(), 0);
+
+        assert_eq!(state.step(None), TickOutcome::Won);
+        assert!(state.is_won());
+        assert_eq!(state.get_snake().len(), 3);
+    }
+
+    #[test]
+    fn speed_effects_stay_above_the_curve_minimum() {
+        let mut settings = settings(4, WallBehaviour::Wrap);
+        settings.tick_rate = 60;
+        settings.speed_curve = SpeedCurve::Capped { step: 2, min: 50 };
+        let mut state = GameState::new(border(), &settings, Some(1));
+        state.speed_effect = Some((70, 100));
+
+        assert_eq!(state.tick_duration(), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use log::info;
+
+use crate::backend::{MemoryBackend, RenderBackend, Style};
+use crate::drawing::Drawer;
+use crate::frame::Cell;
+use crate::replay::Replay;
+use crate::screen::Screen;
+use crate::{Column, Line};
+
+const CELL_WIDTH: u32 = 10;
+const CELL_HEIGHT: u32 = 20;
+const FONT_SIZE: u32 = 16;
+const BACKGROUND: &str = "#1e1e1e";
+const FOREGROUND: &str = "#d0d0d0"; // Cells without a color
+
+/// Returns the hex code of a 256 colors code, like xterm shows it
+fn color_hex(color: u16) -> String {
+    const SYSTEM: [(u8, u8, u8); 16] = [
+        (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+        (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+    ];
+    let (red, green, blue) = match color.min(255) {
+        color @ 0..=15 => SYSTEM[usize::from(color)],
+        color @ 16..=231 => {
+            // 6x6x6 cube
+            let level = |value: u16| if value == 0 { 0 } else { u8::try_from(55 + value * 40).unwrap_or(255) };
+            let index = color - 16;
+            (level(index / 36), level(index / 6 % 6), level(index % 6))
+        }
+        color => {
+            let gray = u8::try_from(8 + (color - 232) * 10).unwrap_or(255);
+            (gray, gray, gray)
+        }
+    };
+    format!("#{red:02x}{green:02x}{blue:02x}")
+}
+
+fn escape_xml(character: char) -> String {
+    match character {
+        '<' => String::from("&lt;"),
+        '>' => String::from("&gt;"),
+        '&' => String::from("&amp;"),
+        c => c.to_string(),
+    }
+}
+
+/// A character shown on a cell from `start` until `end` (until the end of the animation if None)
+struct Glyph {
+    line: Line,
+    column: Column,
+    cell: Cell,
+    start: Duration,
+    end: Option<Duration>,
+}
+
+impl Glyph {
+    fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let x = (u32::from(self.column) - 1) * CELL_WIDTH;
+        let y = u32::from(self.line) * CELL_HEIGHT - CELL_HEIGHT / 4;
+        let Style { color, underline } = self.cell.style;
+        let fill = color.map(color_hex).unwrap_or_else(|| String::from(FOREGROUND));
+        let decoration = if underline { " text-decoration=\"underline\"" } else { "" };
+        let character = escape_xml(self.cell.character);
+
+        // Drawn from the start and never changed: no animation needed
+        if self.start.is_zero() && self.end.is_none() {
+            return writeln!(out, "<text x=\"{x}\" y=\"{y}\" fill=\"{fill}\"{decoration}>{character}</text>");
+        }
+        let end = self.end.map(|end| format!(" end=\"{:.3}s\"", end.as_secs_f64())).unwrap_or_default();
+        writeln!(
+            out,
+            "<text x=\"{x}\" y=\"{y}\" fill=\"{fill}\"{decoration} visibility=\"hidden\">{character}<set attributeName=\"visibility\" to=\"visible\" begin=\"{:.3}s\"{end}/></text>",
+            self.start.as_secs_f64()
+        )
+    }
+}
+
+/// Follows the cells of the screen through time, every change closes the glyph shown before
+struct Timeline {
+    visible: HashMap<(Line, Column), Glyph>,
+    finished: Vec<Glyph>,
+}
+
+impl Timeline {
+    fn new() -> Self {
+        Timeline { visible: HashMap::new(), finished: Vec::new() }
+    }
+
+    /// Compares the screen with the glyphs shown until now
+    fn capture(&mut self, backend: &MemoryBackend, time: Duration) {
+        let (width, height) = backend.size();
+        for line in 1..=height {
+            for column in 1..=width {
+                let cell = backend.get_cell(line, column).unwrap_or_default();
+                let key = (line, column);
+                if self.visible.get(&key).is_some_and(|glyph| glyph.cell == cell) {
+                    continue;
+                }
+                if let Some(mut glyph) = self.visible.remove(&key) {
+                    glyph.end = Some(time);
+                    self.finished.push(glyph);
+                }
+                if cell.character != ' ' || cell.style.underline {
+                    self.visible.insert(key, Glyph { line, column, cell, start: time, end: None });
+                }
+            }
+        }
+    }
+
+    /// Returns every glyph, in drawing order
+    fn into_glyphs(self) -> Vec<Glyph> {
+        let mut glyphs = self.finished;
+        glyphs.extend(self.visible.into_values());
+        glyphs.sort_by_key(|glyph| (glyph.start, glyph.line, glyph.column));
+        glyphs
+    }
+}
+
+/// Plays the replay without a terminal and writes it as an animated SVG, each character shown
+/// and hidden with SMIL at the time it changed in the game
+pub fn export<W: Write>(replay: &Replay, out: &mut W) -> io::Result<()> {
+    // Room for the score above the arena and the seed under it
+    let width = replay.border.end_col + 2;
+    let height = replay.border.end_line + 2;
+    let backend = MemoryBackend::new(width, height);
+    let mut screen = Screen::with_backend(Box::new(backend.clone()));
+    let mut timeline = Timeline::new();
+
+    let settings = &replay.settings;
+    let palette = settings.palette();
+    let glyphs = settings.glyphs;
+    let mut state = replay.start();
+    let mut time = Duration::ZERO;
+    screen.init();
+    Drawer::draw_game(&mut screen, &state, &palette, &glyphs);
+    timeline.capture(&backend, time);
+
+    while state.get_tick() < replay.ticks {
+        let (duration, outcome) = replay.play_tick(&mut screen, &mut state);
+        time += duration;
+        timeline.capture(&backend, time);
+        if outcome.is_over() {
+            break;
+        }
+    }
+
+    let (pixel_width, pixel_height) = (u32::from(width) * CELL_WIDTH, u32::from(height) * CELL_HEIGHT);
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{pixel_width}\" height=\"{pixel_height}\" viewBox=\"0 0 {pixel_width} {pixel_height}\" font-family=\"monospace\" font-size=\"{FONT_SIZE}\" xml:space=\"preserve\">"
+    )?;
+    writeln!(out, "<title>snake, seed {}, score {}</title>", replay.seed, replay.score)?;
+    writeln!(out, "<rect width=\"100%\" height=\"100%\" fill=\"{BACKGROUND}\"/>")?;
+    for glyph in timeline.into_glyphs() {
+        glyph.write(out)?;
+    }
+    writeln!(out, "</svg>")?;
+    info!("[Export] {} ticks exported as SVG", state.get_tick());
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use log::{debug, info};
+
+use crate::backend::Style;
+use crate::clock::Clock;
+use crate::drawing::Drawer;
+use crate::replay::Replay;
+use crate::screen::Screen;
+use crate::state::{GameState, TickOutcome};
+
+/// Playback speeds, as multiples of the recorded one
+const SPEEDS: [f64; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 1;
+const STATUS_WIDTH: usize = 48;
+
+/// Plays a [`Replay`] back on the terminal, drawing it like the game does
+pub struct ReplayViewer {
+    screen: Screen,
+    replay: Replay,
+    state: GameState,
+    ended: bool, // The snake died or filled the board
+    playing: bool,
+    speed: usize, // Index in SPEEDS
+    seek_input: Option<String>, // Tick typed after `g`, until Enter
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay) -> Self {
+        let mut screen = Screen::new();
+        screen.init();
+        screen.hide_cursor();
+
+        let state = replay.start();
+        ReplayViewer {
+            screen,
+            replay,
+            state,
+            ended: false,
+            playing: true,
+            speed: NORMAL_SPEED,
+            seek_input: None,
+        }
+    }
+
+    pub fn run(&mut self) {
+        self.redraw();
+        let mut clock = Clock::new(self.tick_duration());
+        loop {
+            let key = if self.playing && !self.is_over() {
+                match Screen::poll_event(clock.remaining()) {
+                    Ok(true) => Screen::get_event().ok(),
+                    Ok(false) => None,
+                    Err(_) => break,
+                }
+            } else {
+                // Nothing moves, waits for a key
+                match Screen::get_event() {
+                    Ok(event) => Some(event),
+                    Err(_) => break,
+                }
+            };
+
+            match key {
+                Some(Event::Key(key)) => {
+                    let was_playing = self.playing;
+                    if !self.handle_key(key) {
+                        break;
+                    }
+                    if self.playing && !was_playing {
+                        clock.reset();
+                    }
+                    self.draw_status();
+                }
+                Some(Event::Resize(..)) if !self.handle_resize() => break,
+                _ => (),
+            }
+
+            clock.set_tick_duration(self.tick_duration());
+            if self.playing && !self.is_over() && clock.tick() {
+                self.step();
+                self.draw_status();
+            }
+        }
+
+        self.screen.erase_screen();
+        self.screen.flush();
+    }
+
+    /// Returns false when the viewer should close
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        debug!("[Replay] Found key {}", key.code);
+        if let Some(seek_input) = self.seek_input.as_mut() {
+            match key.code {
+                KeyCode::Char(c) if c.is_ascii_digit() && seek_input.len() < 19 => seek_input.push(c),
+                KeyCode::Backspace => {
+                    seek_input.pop();
+                }
+                KeyCode::Enter => {
+                    if let Ok(tick) = seek_input.parse() {
+                        self.seek(tick);
+                    }
+                    self.seek_input = None;
+                }
+                KeyCode::Esc => self.seek_input = None,
+                _ => (),
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char(' ') | KeyCode::Char('p') => {
+                if self.is_over() {
+                    self.seek(0);
+                    self.playing = true;
+                } else {
+                    self.playing = !self.playing;
+                }
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Up => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            KeyCode::Char('-') | KeyCode::Down => self.speed = self.speed.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('.') => {
+                self.playing = false;
+                if !self.is_over() {
+                    self.step();
+                }
+            }
+            KeyCode::Left | KeyCode::Char(',') => {
+                self.playing = false;
+                self.seek(self.state.get_tick().saturating_sub(1));
+            }
+            KeyCode::Char('g') => {
+                self.playing = false;
+                self.seek_input = Some(String::new());
+            }
+            KeyCode::Home => self.seek(0),
+            KeyCode::End => self.seek(self.replay.ticks),
+            KeyCode::Esc | KeyCode::Char('q') => return false,
+            _ => (),
+        }
+        true
+    }
+
+    /// Follows a terminal resize like the game does: the arena is centered again, after waiting
+    /// with a notice while the terminal is too small for it (playback then stays paused).
+    /// Returns false if the viewer was closed from the notice.
+    fn handle_resize(&mut self) -> bool {
+        if !self.screen.update_terminal_size() {
+            return true;
+        }
+        let (border_width, border_height) = self.state.get_border().get_size();
+        let (width, height) = self.screen.get_terminal_size();
+        if width < border_width || height < border_height {
+            self.playing = false;
+            if !self.screen.wait_for_room((border_width, border_height), |code| matches!(code, KeyCode::Esc | KeyCode::Char('q'))) {
+                return false;
+            }
+        }
+
+        let (width, height) = self.screen.get_terminal_size();
+        if let Some(border) = self.state.get_border().centered(width, height) {
+            self.state.move_to(border);
+        }
+        self.redraw();
+        true
+    }
+
+    /// Time between two ticks at the current score, speed effects and playback speed
+    fn tick_duration(&self) -> Duration {
+        self.state.tick_duration().div_f64(SPEEDS[self.speed])
+    }
+
+    fn is_over(&self) -> bool {
+        self.ended || self.state.get_tick() >= self.replay.ticks
+    }
+
+    /// Plays the next tick with the recorded input, returns what happened
+    fn advance(&mut self) -> TickOutcome {
+        let outcome = self.replay.step(&mut self.state);
+        self.end_tick(outcome)
+    }
+
+    /// Plays and draws the next tick
+    fn step(&mut self) {
+        let (_, outcome) = self.replay.play_tick(&mut self.screen, &mut self.state);
+        self.end_tick(outcome);
+    }
+
+    /// Notes whether the game ended with `outcome`, then returns it
+    fn end_tick(&mut self, outcome: TickOutcome) -> TickOutcome {
+        match outcome {
+            TickOutcome::Died(cause) => info!("[Replay] Died: {cause:?}"),
+            TickOutcome::Won => info!("[Replay] Won"),
+            _ => (),
+        }
+        self.ended = outcome.is_over();
+        outcome
+    }
+
+    /// Jumps to `tick`, replaying the game from the start when going back
+    fn seek(&mut self, tick: u64) {
+        let tick = tick.min(self.replay.ticks);
+        if tick < self.state.get_tick() {
+            // The arena stays where the resizes put it
+            let border = self.state.get_border();
+            self.state = self.replay.start();
+            self.state.move_to(border);
+            self.ended = false;
+        }
+        while self.state.get_tick() < tick && !self.ended {
+            self.advance();
+        }
+        self.redraw();
+    }
+
+    fn redraw(&mut self) {
+        self.screen.erase_screen();
+        Drawer::draw_game(&mut self.screen, &self.state, &self.replay.settings.palette(), &self.replay.settings.glyphs);
+        self.draw_status();
+    }
+
+    /// Draws the playback state and the controls under the seed
+    fn draw_status(&mut self) {
+        let border = self.state.get_border();
+        let column = border.start_col + 2;
+        let status = if let Some(seek_input) = &self.seek_input {
+            format!("Seek to tick: {seek_input}_")
+        } else {
+            let state = match (self.playing, self.is_over()) {
+                (_, true) if self.state.is_won() => "Won",
+                (_, true) if self.ended => "Dead",
+                (_, true) => "End",
+                (true, false) => "Playing",
+                (false, false) => "Paused",
+            };
+            format!("{state}  {}x  tick {}/{}", SPEEDS[self.speed], self.state.get_tick(), self.replay.ticks)
+        };
+        let status = format!("{status:<STATUS_WIDTH$}");
+        self.screen.draw_formatted_text(border.end_line + 2, column, status.as_str(), Style::default());
+        let help = "Space play/pause · +/- speed · ←/→ step · g seek · q quit";
+        self.screen.draw_formatted_text(border.end_line + 3, column, help, Style::colored(8));
+        self.screen.flush();
+    }
+}